@@ -0,0 +1,357 @@
+use crate::block::Block;
+use crate::Error;
+
+/// AES block, the state is stored column by column as in FIPS-197:
+/// byte `r + 4*c` is at row r, column c.
+pub type State = Block<16>;
+
+/// Multiplication by x (i.e. 2) in GF(2^8)
+const fn xtime(a: u8) -> u8 {
+    (a << 1) ^ if a & 0x80 != 0 { 0x1B } else { 0 }
+}
+
+/// Multiplication in GF(2^8) modulo x^8 + x^4 + x^3 + x + 1
+const fn gmul(mut a: u8, mut b: u8) -> u8 {
+    let mut p = 0;
+    while b != 0 {
+        if b & 1 != 0 {
+            p ^= a;
+        }
+        a = xtime(a);
+        b >>= 1;
+    }
+    p
+}
+
+/// Multiplicative inverse in GF(2^8), 0 is mapped to 0
+const fn ginv(a: u8) -> u8 {
+    // a^254 = a^-1, by square and multiply
+    let mut result = 1;
+    let mut base = a;
+    let mut e = 254;
+    while e != 0 {
+        if e & 1 != 0 {
+            result = gmul(result, base);
+        }
+        base = gmul(base, base);
+        e >>= 1;
+    }
+    result
+}
+
+const fn make_sbox() -> [u8; 256] {
+    let mut sbox = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let b = ginv(i as u8);
+        sbox[i] = b ^ b.rotate_left(1) ^ b.rotate_left(2) ^ b.rotate_left(3) ^ b.rotate_left(4) ^ 0x63;
+        i += 1;
+    }
+    sbox
+}
+
+const fn invert(sbox: &[u8; 256]) -> [u8; 256] {
+    let mut inv = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        inv[sbox[i] as usize] = i as u8;
+        i += 1;
+    }
+    inv
+}
+
+/// The AES S-box
+pub const SBOX: [u8; 256] = make_sbox();
+/// The inverse of the AES S-box
+pub const INV_SBOX: [u8; 256] = invert(&SBOX);
+
+pub fn sub_bytes(state: &mut State) {
+    for b in state.iter_mut() {
+        *b = SBOX[*b as usize];
+    }
+}
+
+pub fn inv_sub_bytes(state: &mut State) {
+    for b in state.iter_mut() {
+        *b = INV_SBOX[*b as usize];
+    }
+}
+
+/// Rotates row r to the left by r positions
+pub fn shift_rows(state: &mut State) {
+    let s = *state;
+    for r in 1..4 {
+        for c in 0..4 {
+            state[r + 4 * c] = s[r + 4 * ((c + r) % 4)];
+        }
+    }
+}
+
+pub fn inv_shift_rows(state: &mut State) {
+    let s = *state;
+    for r in 1..4 {
+        for c in 0..4 {
+            state[r + 4 * ((c + r) % 4)] = s[r + 4 * c];
+        }
+    }
+}
+
+/// Multiplies each column by the polynomial {03}x^3 + {01}x^2 + {01}x + {02}
+pub fn mix_columns(state: &mut State) {
+    for col in state.chunks_exact_mut(4) {
+        let [a0, a1, a2, a3] = [col[0], col[1], col[2], col[3]];
+        col[0] = xtime(a0) ^ xtime(a1) ^ a1 ^ a2 ^ a3;
+        col[1] = a0 ^ xtime(a1) ^ xtime(a2) ^ a2 ^ a3;
+        col[2] = a0 ^ a1 ^ xtime(a2) ^ xtime(a3) ^ a3;
+        col[3] = xtime(a0) ^ a0 ^ a1 ^ a2 ^ xtime(a3);
+    }
+}
+
+/// Multiplies each column by the polynomial {0b}x^3 + {0d}x^2 + {09}x + {0e}
+pub fn inv_mix_columns(state: &mut State) {
+    for col in state.chunks_exact_mut(4) {
+        let [a0, a1, a2, a3] = [col[0], col[1], col[2], col[3]];
+        col[0] = gmul(a0, 0x0E) ^ gmul(a1, 0x0B) ^ gmul(a2, 0x0D) ^ gmul(a3, 0x09);
+        col[1] = gmul(a0, 0x09) ^ gmul(a1, 0x0E) ^ gmul(a2, 0x0B) ^ gmul(a3, 0x0D);
+        col[2] = gmul(a0, 0x0D) ^ gmul(a1, 0x09) ^ gmul(a2, 0x0E) ^ gmul(a3, 0x0B);
+        col[3] = gmul(a0, 0x0B) ^ gmul(a1, 0x0D) ^ gmul(a2, 0x09) ^ gmul(a3, 0x0E);
+    }
+}
+
+/// AES block cipher (FIPS-197) with a 128, 192 or 256 bit key.
+///
+/// This is a straightforward implementation meant to be studied:
+/// it is not constant time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Aes {
+    round_keys: Vec<State>,
+}
+
+impl Aes {
+    pub const BLOCK_SIZE: usize = 16;
+
+    /// Expands the key, which must be 16, 24 or 32 bytes long
+    pub fn new(key: &[u8]) -> Result<Self, Error> {
+        if !matches!(key.len(), 16 | 24 | 32) {
+            return Err(Error::InvalidKeyLength(key.len()));
+        }
+
+        let nk = key.len() / 4;
+        let rounds = nk + 6;
+        let mut words: Vec<[u8; 4]> = key.chunks_exact(4).map(|w| [w[0], w[1], w[2], w[3]]).collect();
+        let mut rcon = 1u8;
+
+        for i in nk..4 * (rounds + 1) {
+            let mut w = words[i - 1];
+            if i % nk == 0 {
+                w.rotate_left(1);
+                w = w.map(|b| SBOX[b as usize]);
+                w[0] ^= rcon;
+                rcon = xtime(rcon);
+            } else if nk > 6 && i % nk == 4 {
+                w = w.map(|b| SBOX[b as usize]);
+            }
+
+            let prev = words[i - nk];
+            words.push([w[0] ^ prev[0], w[1] ^ prev[1], w[2] ^ prev[2], w[3] ^ prev[3]]);
+        }
+
+        let round_keys = words.chunks_exact(4)
+            .map(|w| Block(std::array::from_fn(|i| w[i / 4][i % 4])))
+            .collect();
+
+        Ok(Aes { round_keys })
+    }
+
+    /// Number of rounds: 10, 12 or 14 depending on the key size
+    pub fn rounds(&self) -> usize {
+        self.round_keys.len() - 1
+    }
+
+    /// The expanded key, one block per round plus the initial one
+    pub fn round_keys(&self) -> &[State] {
+        &self.round_keys
+    }
+
+    pub fn encrypt_block(&self, block: &mut State) {
+        self.encrypt_rounds(block, |_| ());
+    }
+
+    pub fn decrypt_block(&self, block: &mut State) {
+        self.decrypt_rounds(block, |_| ());
+    }
+
+    /// Encrypts a block and returns the state after the initial key
+    /// addition and after each round (the last one is the ciphertext)
+    pub fn encrypt_trace(&self, block: &State) -> Vec<State> {
+        let mut states = Vec::with_capacity(self.round_keys.len());
+        let mut state = *block;
+        self.encrypt_rounds(&mut state, |s| states.push(*s));
+        states
+    }
+
+    /// Decrypts a block and returns the state after each step, as
+    /// for `encrypt_trace`: the last one is the plaintext.
+    pub fn decrypt_trace(&self, block: &State) -> Vec<State> {
+        let mut states = Vec::with_capacity(self.round_keys.len());
+        let mut state = *block;
+        self.decrypt_rounds(&mut state, |s| states.push(*s));
+        states
+    }
+
+    /// Encrypts in place, calling `inspect` with the state after
+    /// the initial key addition and after each round
+    fn encrypt_rounds<F: FnMut(&State)>(&self, state: &mut State, mut inspect: F) {
+        let rounds = self.rounds();
+
+        *state ^= &self.round_keys[0];
+        inspect(state);
+
+        for round in 1..=rounds {
+            sub_bytes(state);
+            shift_rows(state);
+            if round != rounds {
+                mix_columns(state);
+            }
+            *state ^= &self.round_keys[round];
+            inspect(state);
+        }
+    }
+
+    fn decrypt_rounds<F: FnMut(&State)>(&self, state: &mut State, mut inspect: F) {
+        let rounds = self.rounds();
+
+        *state ^= &self.round_keys[rounds];
+        inspect(state);
+
+        for round in 1..=rounds {
+            inv_shift_rows(state);
+            inv_sub_bytes(state);
+            *state ^= &self.round_keys[rounds - round];
+            if round != rounds {
+                inv_mix_columns(state);
+            }
+            inspect(state);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::ToBytes;
+
+    fn block(hex: &str) -> State {
+        Block::try_from(&hex.parse_hex().unwrap()[..]).unwrap()
+    }
+
+    #[test]
+    fn sbox_works() {
+        assert_eq!(SBOX[0x00], 0x63);
+        assert_eq!(SBOX[0x53], 0xED);
+        assert_eq!(SBOX[0xFF], 0x16);
+        assert_eq!(INV_SBOX[0x63], 0x00);
+        assert_eq!(gmul(0x57, 0x13), 0xFE);
+    }
+
+    #[test]
+    fn steps_are_inverted() {
+        let original = block("00112233445566778899aabbccddeeff");
+        type Step = fn(&mut State);
+        let steps: [(Step, Step); 3] = [
+            (sub_bytes, inv_sub_bytes),
+            (shift_rows, inv_shift_rows),
+            (mix_columns, inv_mix_columns),
+        ];
+
+        for (step, inverse) in steps {
+            let mut state = original;
+            step(&mut state);
+            assert_ne!(state, original);
+            inverse(&mut state);
+            assert_eq!(state, original);
+        }
+
+        // FIPS-197 Appendix B, round 1
+        let mut state = block("d42711aee0bf98f1b8b45de51e415230");
+        shift_rows(&mut state);
+        assert_eq!(state, block("d4bf5d30e0b452aeb84111f11e2798e5"));
+        mix_columns(&mut state);
+        assert_eq!(state, block("046681e5e0cb199a48f8d37a2806264c"));
+    }
+
+    #[test]
+    fn key_expansion_works() {
+        // FIPS-197 Appendix A
+        let aes = Aes::new(&"2b7e151628aed2a6abf7158809cf4f3c".parse_hex().unwrap()).unwrap();
+        assert_eq!(aes.rounds(), 10);
+        assert_eq!(aes.round_keys()[1], block("a0fafe1788542cb123a339392a6c7605"));
+        assert_eq!(aes.round_keys()[10], block("d014f9a8c9ee2589e13f0cc8b6630ca6"));
+
+        let key = "603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4".parse_hex().unwrap();
+        let aes = Aes::new(&key).unwrap();
+        assert_eq!(aes.rounds(), 14);
+        assert_eq!(aes.round_keys()[14], block("fe4890d1e6188d0b046df344706c631e"));
+
+        assert_eq!(Aes::new(&[0; 20]), Err(Error::InvalidKeyLength(20)));
+    }
+
+    #[test]
+    fn fips_197_vectors() {
+        let plaintext = block("00112233445566778899aabbccddeeff");
+        let vectors = [
+            ("000102030405060708090a0b0c0d0e0f", "69c4e0d86a7b0430d8cdb78070b4c55a"),
+            ("000102030405060708090a0b0c0d0e0f1011121314151617", "dda97ca4864cdfe06eaf70a0ec0d7191"),
+            ("000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f", "8ea2b7ca516745bfeafc49904b496089"),
+        ];
+
+        for (key, ciphertext) in vectors {
+            let aes = Aes::new(&key.parse_hex().unwrap()).unwrap();
+            let mut state = plaintext;
+
+            aes.encrypt_block(&mut state);
+            assert_eq!(state, block(ciphertext));
+            aes.decrypt_block(&mut state);
+            assert_eq!(state, plaintext);
+        }
+    }
+
+    #[test]
+    fn traces_work() {
+        // FIPS-197 Appendix B
+        let aes = Aes::new(&"2b7e151628aed2a6abf7158809cf4f3c".parse_hex().unwrap()).unwrap();
+        let trace = aes.encrypt_trace(&block("3243f6a8885a308d313198a2e0370734"));
+
+        assert_eq!(trace.len(), 11);
+        assert_eq!(trace[0], block("193de3bea0f4e22b9ac68d2ae9f84808"));
+        assert_eq!(trace[1], block("a49c7ff2689f352b6b5bea43026a5049"));
+        assert_eq!(trace[10], block("3925841d02dc09fbdc118597196a0b32"));
+
+        let trace = aes.decrypt_trace(&trace[10]);
+        assert_eq!(trace[10], block("3243f6a8885a308d313198a2e0370734"));
+    }
+
+    #[test]
+    #[cfg(feature = "openssl")]
+    fn matches_openssl() {
+        use crate::crypto::random_bytes;
+        use openssl::symm::{Cipher, Crypter, Mode};
+
+        for (key_size, cipher) in [(16, Cipher::aes_128_ecb()), (24, Cipher::aes_192_ecb()), (32, Cipher::aes_256_ecb())] {
+            for _ in 0..10 {
+                let key = random_bytes(key_size);
+                let plaintext: State = Block::try_from(&random_bytes(16)[..]).unwrap();
+
+                let mut crypter = Crypter::new(cipher, Mode::Encrypt, &key, None).unwrap();
+                crypter.pad(false);
+                let mut expected = vec![0; 32];
+                let n = crypter.update(&plaintext[..], &mut expected).unwrap();
+                assert_eq!(n, 16);
+
+                let mut state = plaintext;
+                Aes::new(&key).unwrap().encrypt_block(&mut state);
+                assert_eq!(&state[..], &expected[..16]);
+            }
+        }
+    }
+}
@@ -0,0 +1,577 @@
+use std::collections::HashMap;
+use crate::crypto::{xor_byte, xor_string};
+use crate::language::Scorer;
+use crate::parallel::{Engine, Outcome};
+use crate::tools::{load_numbered_lines, LineEncoding};
+use crate::Error;
+
+/// A possible decryption of a single-byte XOR
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candidate {
+    pub key: u8,
+    pub plaintext: Vec<u8>,
+    pub score: f64,
+}
+
+/// The best candidates for a ciphertext, sorted by decreasing score
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ranking {
+    pub candidates: Vec<Candidate>,
+    /// Score difference between the best and the second best
+    /// candidate: the larger, the more confident the guess.
+    pub margin: f64,
+}
+
+impl Ranking {
+    /// Returns the highest scoring candidate, if any was asked for
+    pub fn best(&self) -> Option<&Candidate> {
+        self.candidates.first()
+    }
+}
+
+/// Best single-byte XOR candidate of a line of a file
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineCandidate {
+    /// Index of the ciphertext among the lines (blank lines of a
+    /// file are not counted)
+    pub index: usize,
+    /// Number of the line in the file, starting at 1 (`index + 1`
+    /// when the lines are not read from a file)
+    pub line: usize,
+    pub ciphertext: Vec<u8>,
+    pub candidate: Candidate,
+    pub margin: f64,
+}
+
+/// Tries all the 256 keys of a single-byte XOR and returns the
+/// `top` best candidates according to the scorer.
+pub fn break_single_byte_xor<S: Scorer + ?Sized>(ciphertext: &[u8], scorer: &S, top: usize) -> Ranking {
+    let mut candidates: Vec<Candidate> = (0..=255u8)
+        .map(|key| {
+            let plaintext = xor_byte(ciphertext, key);
+            let score = scorer.score(&plaintext);
+            Candidate { key, plaintext, score }
+        })
+        .collect();
+
+    // Sorting is stable, so ties are broken by the lowest key
+    candidates.sort_by(|a, b| b.score.total_cmp(&a.score));
+    let margin = candidates[0].score - candidates[1].score;
+    candidates.truncate(top);
+
+    Ranking { candidates, margin }
+}
+
+/// Breaks each ciphertext as a single-byte XOR, and returns the
+/// `top` ones whose best candidate scores highest, e.g. to find
+/// which lines are encrypted text among random ones. The lines are
+/// split among the threads of the engine.
+pub fn rank_lines<B, S>(ciphertexts: &[B], scorer: &S, top: usize, engine: &Engine) -> Vec<LineCandidate>
+where
+    B: AsRef<[u8]> + Sync,
+    S: Scorer + ?Sized,
+{
+    let indexed: Vec<(usize, &B)> = ciphertexts.iter().enumerate().collect();
+    let rank = |&(index, ciphertext): &(usize, &B)| {
+        let ranking = break_single_byte_xor(ciphertext.as_ref(), scorer, 1);
+        Outcome::Candidate(vec![LineCandidate {
+            index,
+            line: index + 1,
+            ciphertext: ciphertext.as_ref().to_vec(),
+            // One candidate was asked for, and there are 256
+            candidate: ranking.candidates.into_iter().next().unwrap(),
+            margin: ranking.margin,
+        }])
+    };
+
+    // Each thread keeps its best lines, ties go to the first line
+    let keep_top = |mut a: Vec<LineCandidate>, b: Vec<LineCandidate>| {
+        a.extend(b);
+        a.sort_by(|a, b| b.candidate.score.total_cmp(&a.candidate.score).then(a.index.cmp(&b.index)));
+        a.truncate(top);
+        a
+    };
+
+    engine.search(&indexed, rank, keep_top).unwrap_or_default()
+}
+
+/// Loads a file with a ciphertext per line and ranks its lines,
+/// see `rank_lines`
+pub fn rank_file<S: Scorer + ?Sized>(filename: &str, encoding: LineEncoding, scorer: &S, top: usize, engine: &Engine) -> Result<Vec<LineCandidate>, Error> {
+    let (numbers, lines): (Vec<usize>, Vec<Vec<u8>>) = load_numbered_lines(filename, encoding)?.into_iter().unzip();
+    let mut ranked = rank_lines(&lines, scorer, top, engine);
+    for candidate in &mut ranked {
+        candidate.line = numbers[candidate.index];
+    }
+    Ok(ranked)
+}
+
+/// A keysize of a repeating-key XOR with its statistics
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KeySize {
+    pub size: usize,
+    /// Hamming distance between blocks of this size, in bits per
+    /// byte averaged over all the pairs of blocks (lower is better)
+    pub distance: f64,
+    /// Index of coincidence of the columns (higher is better)
+    pub coincidence: f64,
+}
+
+/// Ranks the keysizes from 1 to `max_size` by how likely they are
+/// for a repeating-key XOR of the ciphertext, best first. Each size
+/// is ranked by both its normalized Hamming distance and its index
+/// of coincidence, and the sum of the two positions decides. Sizes
+/// leaving fewer than two blocks are skipped.
+pub fn rank_keysizes(ciphertext: &[u8], max_size: usize) -> Vec<KeySize> {
+    let mut sizes: Vec<KeySize> = (1..=max_size.min(ciphertext.len() / 2))
+        .map(|size| KeySize {
+            size,
+            distance: block_distance(ciphertext, size),
+            coincidence: column_coincidence(ciphertext, size),
+        })
+        .collect();
+
+    let mut positions: HashMap<usize, usize> = HashMap::new();
+    sizes.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+    for (i, k) in sizes.iter().enumerate() {
+        positions.insert(k.size, i);
+    }
+    sizes.sort_by(|a, b| b.coincidence.total_cmp(&a.coincidence));
+    for (i, k) in sizes.iter().enumerate() {
+        *positions.get_mut(&k.size).unwrap() += i;
+    }
+
+    // Ties go to the smallest size, multiples of the period score
+    // as well as the period itself
+    sizes.sort_by_key(|k| (positions[&k.size], k.size));
+    sizes
+}
+
+/// Average Hamming distance, in bits per byte, of all the pairs of
+/// whole blocks of the given size
+fn block_distance(bytes: &[u8], size: usize) -> f64 {
+    let blocks: Vec<&[u8]> = bytes.chunks_exact(size).collect();
+    let mut total = 0u64;
+    let mut pairs = 0u64;
+
+    for (i, a) in blocks.iter().enumerate() {
+        for b in &blocks[i + 1..] {
+            total += a.iter().zip(*b).map(|(x, y)| (x ^ y).count_ones() as u64).sum::<u64>();
+            pairs += 1;
+        }
+    }
+
+    total as f64 / (pairs * size as u64) as f64
+}
+
+/// Average index of coincidence of the columns of the given size
+fn column_coincidence(bytes: &[u8], size: usize) -> f64 {
+    let columns: Vec<Vec<u8>> = (0..size)
+        .map(|i| bytes[i..].iter().step_by(size).copied().collect())
+        .collect();
+
+    columns.iter().map(|c| index_of_coincidence(c)).sum::<f64>() / size as f64
+}
+
+/// Probability that two bytes picked at random from the text are
+/// equal: about 1/256 for random data, much higher for text. It is
+/// not changed by a single-byte XOR.
+pub fn index_of_coincidence(bytes: &[u8]) -> f64 {
+    if bytes.len() < 2 {
+        return 0.0;
+    }
+
+    let mut counts = [0u64; 256];
+    for &b in bytes {
+        counts[b as usize] += 1;
+    }
+
+    let n = bytes.len() as u64;
+    counts.iter().map(|c| c * c.saturating_sub(1)).sum::<u64>() as f64 / (n * (n - 1)) as f64
+}
+
+/// Returns the shortest period of a key, e.g. 2 for "abab"
+pub fn key_period(key: &[u8]) -> usize {
+    (1..key.len())
+        .find(|&p| key.len().is_multiple_of(p) && key.iter().enumerate().all(|(i, &b)| b == key[i % p]))
+        .unwrap_or(key.len())
+}
+
+/// A solution to a repeating-key XOR
+#[derive(Debug, Clone, PartialEq)]
+pub struct RepeatingXorSolution {
+    pub key: Vec<u8>,
+    pub plaintext: Vec<u8>,
+    pub score: f64,
+}
+
+/// Breaks repeating-key XOR: ranks the keysizes, solves each column
+/// of the best ones as a single-byte XOR, and keeps the plaintext
+/// the scorer likes most.
+pub struct RepeatingXorBreaker<'a> {
+    scorer: &'a dyn Scorer,
+    column_scorer: &'a dyn Scorer,
+    max_keysize: usize,
+    candidates: usize,
+    engine: Engine,
+}
+
+impl<'a> RepeatingXorBreaker<'a> {
+    /// Creates a breaker trying the 3 best keysizes up to 40,
+    /// on all the available cores
+    pub fn new(scorer: &'a dyn Scorer) -> Self {
+        RepeatingXorBreaker { scorer, column_scorer: scorer, max_keysize: 40, candidates: 3, engine: Engine::default() }
+    }
+
+    /// Uses a different scorer for the columns. Their bytes are not
+    /// contiguous in the plaintext, so a scorer looking at single
+    /// bytes only is usually better there.
+    pub fn with_column_scorer(mut self, column_scorer: &'a dyn Scorer) -> Self {
+        self.column_scorer = column_scorer;
+        self
+    }
+
+    pub fn with_max_keysize(mut self, max_keysize: usize) -> Self {
+        self.max_keysize = max_keysize;
+        self
+    }
+
+    /// Sets how many of the best keysizes are tried
+    pub fn with_candidates(mut self, candidates: usize) -> Self {
+        self.candidates = candidates;
+        self
+    }
+
+    /// Sets the engine the columns are solved with
+    pub fn with_engine(mut self, engine: Engine) -> Self {
+        self.engine = engine;
+        self
+    }
+
+    /// Fails if the ciphertext is too short to hold two blocks
+    /// of any keysize
+    pub fn solve(&self, ciphertext: &[u8]) -> Result<RepeatingXorSolution, Error> {
+        rank_keysizes(ciphertext, self.max_keysize)
+            .iter()
+            .take(self.candidates.max(1))
+            .map(|k| self.solve_keysize(ciphertext, k.size))
+            .max_by(|a, b| a.score.total_cmp(&b.score))
+            .ok_or(Error::InvalidLength)
+    }
+
+    fn solve_keysize(&self, ciphertext: &[u8], size: usize) -> RepeatingXorSolution {
+        let columns: Vec<usize> = (0..size).collect();
+        let solve_column = |&i: &usize| {
+            let column: Vec<u8> = ciphertext[i..].iter().step_by(size).copied().collect();
+            Outcome::Candidate(vec![(i, break_single_byte_xor(&column, self.column_scorer, 1).candidates[0].key)])
+        };
+
+        // Every column gives a candidate, so there is a result
+        let mut solved = self.engine.search(&columns, solve_column, |mut a, b| { a.extend(b); a }).unwrap();
+        solved.sort_unstable();
+        let mut key: Vec<u8> = solved.into_iter().map(|(_, k)| k).collect();
+
+        // A multiple of the period was tried, the key repeats itself
+        key.truncate(key_period(&key));
+
+        let plaintext = xor_string(ciphertext, &key);
+        let score = self.scorer.score(&plaintext);
+
+        RepeatingXorSolution { key, plaintext, score }
+    }
+}
+
+/// Shorthand for a breaker with default settings, see `RepeatingXorBreaker`
+pub fn break_repeating_xor(ciphertext: &[u8], scorer: &dyn Scorer) -> Result<RepeatingXorSolution, Error> {
+    RepeatingXorBreaker::new(scorer).solve(ciphertext)
+}
+
+/// Fraction of equal bytes between the ciphertext and itself shifted
+/// by 1 to `max_shift` positions. A repeating-key XOR of text shows
+/// peaks at the multiples of the key period, since there the key
+/// cancels out and the plaintext coincidence remains.
+pub fn autocorrelation(ciphertext: &[u8], max_shift: usize) -> Vec<f64> {
+    (1..=max_shift.min(ciphertext.len().saturating_sub(1)))
+        .map(|shift| {
+            let equal = ciphertext.iter().zip(&ciphertext[shift..]).filter(|(a, b)| a == b).count();
+            equal as f64 / (ciphertext.len() - shift) as f64
+        })
+        .collect()
+}
+
+/// Finds the period of a repeating-key XOR up to `max_period` from
+/// the autocorrelation. Each period is scored by how much the
+/// coincidence at its multiples exceeds the one at the other shifts,
+/// and the shortest one scoring about the best is returned, provided
+/// its coincidence looks like text's.
+pub fn detect_period(ciphertext: &[u8], max_period: usize) -> Option<usize> {
+    // Each period needs at least two multiples, to avoid flukes
+    let correlation = autocorrelation(ciphertext, 2 * max_period);
+    let mean = |c: &[f64]| c.iter().sum::<f64>() / c.len() as f64;
+
+    let periods: Vec<(usize, f64, f64)> = (1..=correlation.len() / 2)
+        .map(|p| {
+            let mut multiples = Vec::new();
+            let mut others = Vec::new();
+            for (shift, &c) in (1usize..).zip(&correlation) {
+                if shift.is_multiple_of(p) { multiples.push(c) } else { others.push(c) }
+            }
+
+            // Random bytes are equal with probability 1/256
+            let baseline = if others.is_empty() { 1.0 / 256.0 } else { mean(&others) };
+            (p, mean(&multiples), baseline)
+        })
+        .collect();
+
+    let best = periods.iter().map(|&(_, m, b)| m - b).fold(0.0, f64::max);
+    let (period, coincidence, baseline) = periods.into_iter().find(|&(_, m, b)| m - b >= 0.8 * best)?;
+
+    // English text has a coincidence of about 0.065, random bytes of
+    // 1/256: half the former leaves room for short texts and other
+    // languages while staying far above the latter
+    (coincidence > 2.0 * baseline && coincidence > 0.03).then_some(period)
+}
+
+/// Keystream revealed by a known plaintext at the given offset.
+/// Fails if it doesn't fit in the ciphertext.
+pub fn keystream_at(ciphertext: &[u8], known: &[u8], offset: usize) -> Result<Vec<u8>, Error> {
+    let end = offset.checked_add(known.len()).filter(|&end| end <= ciphertext.len()).ok_or(Error::InvalidLength)?;
+
+    Ok(ciphertext[offset..end].iter().zip(known).map(|(c, p)| c ^ p).collect())
+}
+
+/// A repeating key recovered from a known plaintext
+#[derive(Debug, Clone, PartialEq)]
+pub struct KnownPlaintextSolution {
+    /// Offset of the known plaintext in the ciphertext
+    pub offset: usize,
+    /// The key, aligned to the start of the ciphertext
+    pub key: Vec<u8>,
+    pub plaintext: Vec<u8>,
+    pub score: f64,
+}
+
+/// Recovers a repeating key of the given period from a known
+/// plaintext. If the offset of the known plaintext is None, every
+/// offset is tried: those where the revealed keystream doesn't
+/// repeat with the period are discarded, and the scorer picks the
+/// best decryption among the others.
+///
+/// The known plaintext must be at least as long as the period,
+/// otherwise `Error::KnownPlaintextTooShort` is returned.
+pub fn recover_key_with_period<S: Scorer + ?Sized>(
+    ciphertext: &[u8],
+    known: &[u8],
+    offset: Option<usize>,
+    period: usize,
+    scorer: &S,
+) -> Result<KnownPlaintextSolution, Error> {
+    if period == 0 {
+        return Err(Error::InvalidLength);
+    }
+    if known.len() < period {
+        return Err(Error::KnownPlaintextTooShort(known.len(), period));
+    }
+    if known.len() > ciphertext.len() {
+        return Err(Error::InvalidLength);
+    }
+
+    let offsets = match offset {
+        Some(o) => o..=o,
+        None => 0..=ciphertext.len() - known.len(),
+    };
+
+    let mut best: Option<KnownPlaintextSolution> = None;
+    for offset in offsets {
+        let keystream = keystream_at(ciphertext, known, offset)?;
+        if keystream.iter().zip(&keystream[period..]).any(|(a, b)| a != b) {
+            continue;
+        }
+
+        let mut key = vec![0; period];
+        for (i, &k) in keystream[..period].iter().enumerate() {
+            key[(offset + i) % period] = k;
+        }
+
+        let plaintext = xor_string(ciphertext, &key);
+        let score = scorer.score(&plaintext);
+        if best.as_ref().is_none_or(|b| score > b.score) {
+            best = Some(KnownPlaintextSolution { offset, key, plaintext, score });
+        }
+    }
+
+    // A known offset where the keystream doesn't repeat means the
+    // period is wrong
+    best.ok_or(Error::PeriodNotFound)
+}
+
+/// Recovers a repeating key from a known plaintext, detecting the
+/// period (up to 40) by autocorrelation. See `recover_key_with_period`.
+pub fn recover_repeating_key<S: Scorer + ?Sized>(
+    ciphertext: &[u8],
+    known: &[u8],
+    offset: Option<usize>,
+    scorer: &S,
+) -> Result<KnownPlaintextSolution, Error> {
+    let period = detect_period(ciphertext, 40).ok_or(Error::PeriodNotFound)?;
+    recover_key_with_period(ciphertext, known, offset, period, scorer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::language::{LanguageModel, ScoreWeights};
+    use crate::tools::load_base64_file;
+
+    #[test]
+    fn break_single_byte_xor_works() {
+        let model = LanguageModel::english();
+        let plaintext = b"Cooking MC's like a pound of bacon";
+
+        for key in [0x00, 0x58, 0xFF] {
+            let ranking = break_single_byte_xor(&xor_byte(plaintext, key), &model, 3);
+
+            assert_eq!(ranking.candidates.len(), 3);
+            assert_eq!(ranking.best().unwrap().key, key);
+            assert_eq!(ranking.best().unwrap().plaintext, plaintext);
+            assert!(ranking.candidates[0].score >= ranking.candidates[1].score);
+            assert!(ranking.candidates[1].score >= ranking.candidates[2].score);
+            assert!(ranking.margin > 0.0);
+        }
+    }
+
+    #[test]
+    fn margin_reflects_confidence() {
+        let model = LanguageModel::english();
+        let ranking = break_single_byte_xor(b"", &model, 0);
+
+        assert_eq!(ranking.best(), None);
+        assert_eq!(ranking.margin, 0.0);
+
+        let ranking = break_single_byte_xor(&xor_byte(b"This is plain English text", 7), &model, 1);
+        assert!(ranking.margin > 0.5);
+    }
+
+    #[test]
+    fn rank_file_works() {
+        let model = LanguageModel::english();
+        let lines = rank_file("./res/s01e04", LineEncoding::Hex, &model, 5, &Engine::default()).unwrap();
+
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[0].candidate.plaintext, b"Now that the party is jumping\n");
+        assert_eq!(lines[0].candidate.key, 0x35);
+        assert_eq!(lines[0].index, 170);
+        assert_eq!(lines[0].line, 171);
+        assert!(lines[0].candidate.score > lines[1].candidate.score);
+
+        let scorer: &dyn Scorer = &model;
+        assert_eq!(rank_lines(&[b"abc".to_vec()], scorer, 10, &Engine::new(1)).len(), 1);
+        assert_eq!(rank_lines(&lines.iter().map(|l| &l.ciphertext).collect::<Vec<_>>(), scorer, 2, &Engine::new(3))[0].index, 0);
+    }
+
+    #[test]
+    fn index_of_coincidence_works() {
+        assert_eq!(index_of_coincidence(b"aaaa"), 1.0);
+        assert_eq!(index_of_coincidence(b"abcd"), 0.0);
+        assert_eq!(index_of_coincidence(b"a"), 0.0);
+
+        let text = b"The quick brown fox jumps over the lazy dog, again and again";
+        assert_eq!(index_of_coincidence(text), index_of_coincidence(&xor_byte(text, 0x42)));
+    }
+
+    #[test]
+    fn key_period_works() {
+        assert_eq!(key_period(b"abcabc"), 3);
+        assert_eq!(key_period(b"aaaa"), 1);
+        assert_eq!(key_period(b"abcab"), 5);
+        assert_eq!(key_period(b""), 0);
+    }
+
+    #[test]
+    fn rank_keysizes_works() {
+        let bytes = load_base64_file("./res/s01e06").unwrap();
+        let sizes = rank_keysizes(&bytes, 40);
+
+        assert_eq!(sizes.len(), 40);
+        assert_eq!(sizes[0].size, 29);
+        assert!(rank_keysizes(b"a", 40).is_empty());
+    }
+
+    #[test]
+    fn break_repeating_xor_works() {
+        let model = LanguageModel::english();
+        let bytes = load_base64_file("./res/s01e06").unwrap();
+        let solution = break_repeating_xor(&bytes, &model).unwrap();
+
+        assert_eq!(solution.key, b"Terminator X: Bring the noise");
+        assert!(solution.plaintext.starts_with(b"I'm back and I'm ringin' the bell"));
+        assert_eq!(break_repeating_xor(b"a", &model), Err(Error::InvalidLength));
+    }
+
+    #[test]
+    fn short_ciphertexts_are_broken() {
+        let model = LanguageModel::english();
+        let columns = model.clone().with_weights(ScoreWeights::UNIGRAM_ONLY);
+        let plaintext = b"Meet me at the old mill by the river at midnight, and bring the documents with you. Come alone.";
+        let ciphertext = xor_string(plaintext, b"\xFFkey");
+
+        let solution = RepeatingXorBreaker::new(&model)
+            .with_column_scorer(&columns)
+            .with_max_keysize(12)
+            .with_candidates(6)
+            .with_engine(Engine::new(1))
+            .solve(&ciphertext)
+            .unwrap();
+
+        assert_eq!(solution.key, b"\xFFkey");
+        assert_eq!(solution.plaintext, plaintext);
+    }
+
+    const LETTER: &[u8] = b"Dear Anna, the shipment will arrive on Monday at the north gate. \
+        Please make sure that the guards are told in advance, and that the trucks are unloaded before noon. \
+        The documents for the customs office are in the blue folder on my desk. Regards, Paul";
+
+    #[test]
+    fn detect_period_works() {
+        for key in [&b"k"[..], b"secret", b"\x00\x11\x22\x33\x44\x55\x66\x77\x88\x99\xaa\xbb\xcc"] {
+            assert_eq!(detect_period(&xor_string(LETTER, key), 20), Some(key.len()));
+        }
+
+        let bytes = load_base64_file("./res/s01e06").unwrap();
+        assert_eq!(detect_period(&bytes, 40), Some(29));
+
+        let random: Vec<u8> = (0..200u32).map(|i| (i.wrapping_mul(2654435761) >> 13) as u8).collect();
+        assert_eq!(detect_period(&random, 20), None);
+        assert_eq!(detect_period(b"", 20), None);
+        assert_eq!(autocorrelation(b"abab", 10), [0.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn known_plaintext_recovers_key() {
+        let model = LanguageModel::english();
+        let key = b"Sesame!";
+        let ciphertext = xor_string(LETTER, key);
+
+        let solution = recover_repeating_key(&ciphertext, b"Dear Anna", Some(0), &model).unwrap();
+        assert_eq!(solution.key, key);
+        assert_eq!(solution.plaintext, LETTER);
+
+        let solution = recover_repeating_key(&ciphertext, b"customs office", None, &model).unwrap();
+        assert_eq!(solution.key, key);
+        assert_eq!(solution.offset, 187);
+
+        assert_eq!(keystream_at(&ciphertext, b"Dear", 0).unwrap(), b"Sesa");
+        assert_eq!(keystream_at(b"ab", b"abc", 0), Err(Error::InvalidLength));
+        assert_eq!(keystream_at(b"ab", b"a", usize::MAX), Err(Error::InvalidLength));
+    }
+
+    #[test]
+    fn short_known_plaintext_is_reported() {
+        let model = LanguageModel::english();
+        let ciphertext = xor_string(LETTER, b"Sesame!");
+
+        assert_eq!(recover_repeating_key(&ciphertext, b"Dear", Some(0), &model), Err(Error::KnownPlaintextTooShort(4, 7)));
+        assert_eq!(recover_key_with_period(&ciphertext, b"Dear Anna", Some(0), 8, &model), Err(Error::PeriodNotFound));
+        assert_eq!(recover_key_with_period(b"abc", b"abcd", None, 2, &model), Err(Error::InvalidLength));
+    }
+}
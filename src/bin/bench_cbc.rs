@@ -0,0 +1,42 @@
+use std::env;
+use std::time::{Duration, Instant};
+use cryptopals::aes::Aes;
+use cryptopals::cipher::OpensslAes;
+use cryptopals::crypto::{aes_cbc_decrypt, aes_cbc_encrypt, cbc_decrypt, cbc_encrypt, random_bytes};
+use cryptopals::padding::Pkcs7;
+
+// Compares the throughput of the AES-CBC implementations: the generic
+// mode over openssl, which sets up a context for every block (as
+// aes_cbc_* used to), the generic mode over our AES, and aes_cbc_*,
+// which use a single openssl context.
+//
+// Usage: cargo run --release --bin bench_cbc -- [size in MB]
+fn main() {
+    let size: usize = env::args().nth(1).and_then(|s| s.parse().ok()).unwrap_or(16);
+    let plaintext = random_bytes(size << 20);
+    let key = random_bytes(16);
+    let iv = random_bytes(16);
+
+    let openssl = OpensslAes::new(&key).unwrap();
+    let aes = Aes::new(&key).unwrap();
+    let ciphertext = aes_cbc_encrypt(&plaintext, &key, &iv, &Pkcs7).unwrap();
+
+    println!("AES-128-CBC on {} MB", size);
+    report("per-block context", "encrypt", plaintext.len(), || cbc_encrypt(&openssl, &plaintext, &iv, &Pkcs7).unwrap());
+    report("per-block context", "decrypt", plaintext.len(), || cbc_decrypt(&openssl, &ciphertext, &iv, &Pkcs7).unwrap());
+    report("pure Rust", "encrypt", plaintext.len(), || cbc_encrypt(&aes, &plaintext, &iv, &Pkcs7).unwrap());
+    report("pure Rust", "decrypt", plaintext.len(), || cbc_decrypt(&aes, &ciphertext, &iv, &Pkcs7).unwrap());
+    report("single context", "encrypt", plaintext.len(), || aes_cbc_encrypt(&plaintext, &key, &iv, &Pkcs7).unwrap());
+    report("single context", "decrypt", plaintext.len(), || aes_cbc_decrypt(&ciphertext, &key, &iv, &Pkcs7).unwrap());
+}
+
+/// Runs `f` and prints its throughput on `len` bytes
+fn report<F: Fn() -> Vec<u8>>(name: &str, operation: &str, len: usize, f: F) {
+    let start = Instant::now();
+    let output = f();
+    let elapsed = start.elapsed().max(Duration::from_nanos(1));
+
+    // The output is checked so that the work can't be optimized away
+    assert!(output.len() >= len);
+    println!("{:>18} {}: {:8.1} MB/s", name, operation, len as f64 / (1 << 20) as f64 / elapsed.as_secs_f64());
+}
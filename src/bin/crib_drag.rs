@@ -0,0 +1,25 @@
+use std::env;
+use std::io;
+use std::process;
+use cryptopals::crib::CribDragger;
+use cryptopals::language::LanguageModel;
+use cryptopals::tools::{load_lines, LineEncoding};
+
+// Interactive crib dragging over ciphertexts sharing a keystream:
+//   cargo run --bin crib_drag -- <file> [language]
+// The file has a hex or base64 ciphertext per line.
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.is_empty() || args.len() > 2 {
+        eprintln!("Usage: crib_drag <file> [language]");
+        process::exit(1);
+    }
+
+    let ciphertexts = load_lines(&args[0], LineEncoding::Auto).unwrap();
+    let language = args.get(1).map(String::as_str).unwrap_or("english");
+    let model = LanguageModel::builtin(language).expect("Unknown language");
+
+    CribDragger::new(ciphertexts)
+        .interactive(&model, io::stdin().lock(), io::stdout())
+        .unwrap();
+}
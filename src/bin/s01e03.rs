@@ -1,21 +1,17 @@
-use cryptopals::tools::{english_score, ToBytes};
-use cryptopals::crypto::xor_char;
+use std::env;
+use cryptopals::analysis::break_single_byte_xor;
+use cryptopals::language::LanguageModel;
+use cryptopals::tools::ToBytes;
 
 fn main() {
     let bytes = "1b37373331363f78151b7f2b783431333d78397828372d363c78373e783a393b3736".parse_hex().unwrap();
+    // The language of the plaintext can be given as argument
+    let language = env::args().nth(1).unwrap_or_else(|| "english".to_string());
+    let model = LanguageModel::builtin(&language).expect("Unknown language");
 
-    let mut highest_scoring = "".to_string();
-    let mut highest_score = 0.0;
-
-    for c in 'A'..'z' {
-        let xor_string = xor_char(&bytes, c as u8);
-        let score = english_score(&xor_string);
-
-        if score > highest_score {
-            highest_score = score;
-            highest_scoring = xor_string;   
-        }
+    let ranking = break_single_byte_xor(&bytes, &model, 3);
+    for c in &ranking.candidates {
+        println!("(Key: {:#04x}, Score: {:.2}): {}", c.key, c.score, String::from_utf8_lossy(&c.plaintext));
     }
-
-    println!("(Score: {}): {}", highest_score, highest_scoring);
-}
\ No newline at end of file
+    println!("Margin: {:.2}", ranking.margin);
+}
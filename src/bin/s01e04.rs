@@ -1,32 +1,17 @@
-use cryptopals::crypto::xor_char;
-use cryptopals::tools::{english_score, ToBytes};
-use std::io::{BufReader, BufRead};
-use std::fs::File;
+use std::env;
+use cryptopals::analysis::rank_file;
+use cryptopals::language::LanguageModel;
+use cryptopals::parallel::Engine;
+use cryptopals::tools::{LineEncoding, AsString};
 
 fn main() {
-    let file = File::open("./res/s01e04").unwrap();
-    let reader = BufReader::new(file);
+    // The language of the plaintext can be given as argument
+    let language = env::args().nth(1).unwrap_or_else(|| "english".to_string());
+    let model = LanguageModel::builtin(&language).expect("Unknown language");
 
-    let mut highest_scoring_ciphertext = "".to_string();
-    let mut highest_scoring = "".to_string();
-    let mut highest_score = f64::NEG_INFINITY;
+    let lines = rank_file("./res/s01e04", LineEncoding::Hex, &model, 1, &Engine::default()).unwrap();
+    let best = &lines[0];
 
-    for line in reader.lines() {
-        let l = line.unwrap();
-        let bytes = &l.parse_hex().unwrap();
-    
-        for c in 0..0xFF {
-            let xor_string = xor_char(&bytes, c);
-            let score = english_score(&xor_string);
-    
-            if score > highest_score {
-                highest_scoring_ciphertext = l.to_owned();
-                highest_score = score;
-                highest_scoring = xor_string;   
-            }
-        }
-    }
-
-    println!("(Score: {:.2}): {}", highest_score, highest_scoring_ciphertext);
-    println!("{:?}", highest_scoring);
+    println!("(Line: {}, Key: {:#04x}, Score: {:.2}): {}", best.line, best.candidate.key, best.candidate.score, best.ciphertext.as_hex());
+    println!("{:?}", String::from_utf8_lossy(&best.candidate.plaintext));
 }
@@ -10,5 +10,5 @@ fn main() {
 
     assert_eq!(result, expected);
     println!("{}", result.as_hex());
-    println!("{}", "OK!");
+    println!("OK!");
 }
\ No newline at end of file
@@ -1,9 +1,10 @@
+use cryptopals::crypto::aes_ecb_decrypt;
+use cryptopals::padding::Pkcs7;
 use cryptopals::tools::load_base64_file;
-use openssl::symm::{Cipher, decrypt};
 
 fn main() {
-    let bytes = load_base64_file("./res/s01e07");
+    let bytes = load_base64_file("./res/s01e07").unwrap();
     let key = b"YELLOW SUBMARINE";
-    let plaintext = decrypt(Cipher::aes_128_ecb(), key, None, &bytes).unwrap();
+    let plaintext = aes_ecb_decrypt(&bytes, key, &Pkcs7).unwrap();
     println!("{}", String::from_utf8_lossy(&plaintext));
 }
\ No newline at end of file
@@ -1,31 +1,20 @@
-use std::io::{BufReader, BufRead};
-use std::fs::File;
-use openssl::symm::Cipher;
+use cryptopals::aes::Aes;
+use cryptopals::hexdump::{Hexdump, repeated_blocks};
+use cryptopals::tools::{load_lines, LineEncoding};
 
 fn main() {
-    let file = File::open("./res/s01e08").unwrap();
-    let reader = BufReader::new(file);
+    let ciphertexts = load_lines("./res/s01e08", LineEncoding::Hex).unwrap();
 
-    let block_size = Cipher::aes_128_ecb().block_size();
+    let block_size = Aes::BLOCK_SIZE;
+    let dump = Hexdump::new(block_size).unwrap().with_color(true);
 
-    for (ln, line) in reader.lines().enumerate() {
-        let line = line.unwrap();
-        let mut max_count = 0;
+    for (ln, bytes) in ciphertexts.iter().enumerate() {
+        // ECB encrypts equal plaintext blocks to equal ciphertext blocks
+        let repeated = repeated_blocks(bytes, block_size).unwrap().iter().filter(|g| g.is_some()).count();
 
-        for i in (0..line.len()).step_by(block_size) {
-            let mut count = 0;
-
-            for j in ((i+block_size)..line.len()).step_by(block_size) {
-                if line[i..i+block_size] == line[j..j+block_size] {
-                    count += 1;
-                }
-            }
-
-            max_count = if max_count > count { max_count } else { count };
-        }
-
-        if max_count > 0 {
-            println!("Ciphertext n. {} has a block repeated {} times", ln+1, max_count+1);
+        if repeated > 0 {
+            println!("Ciphertext n. {} has {} repeated blocks", ln+1, repeated);
+            println!("{}", dump.dump(bytes));
         }
     }
-}
\ No newline at end of file
+}
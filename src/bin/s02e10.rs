@@ -1,12 +1,14 @@
 use cryptopals::crypto::aes_cbc_decrypt;
+use cryptopals::key::{Iv, Key};
+use cryptopals::padding::Pkcs7;
 use cryptopals::tools::load_base64_file;
 
 fn main() {
-    let bytes = load_base64_file("./res/s02e10");
+    let bytes = load_base64_file("./res/s02e10").unwrap();
 
-    let iv = [0u8; 16];
-    let key = b"YELLOW SUBMARINE";
+    let iv = Iv::<16>::from([0; 16]);
+    let key = Key::<16>::from(*b"YELLOW SUBMARINE");
 
-    let plaintext = aes_cbc_decrypt(&bytes, key, &iv).unwrap();
+    let plaintext = aes_cbc_decrypt(&bytes, &key, &iv, &Pkcs7).unwrap();
     println!("{}", String::from_utf8_lossy(&plaintext)); 
 }
\ No newline at end of file
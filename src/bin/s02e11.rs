@@ -1,5 +1,7 @@
-use cryptopals::crypto::{random_bytes, aes_cbc_encrypt};
-use openssl::symm::{encrypt, Cipher};
+use cryptopals::crypto::{random_bytes, aes_cbc_encrypt, aes_ecb_encrypt};
+use cryptopals::hexdump::repeated_blocks;
+use cryptopals::key::{Iv, Key};
+use cryptopals::padding::Pkcs7;
 use rand::Rng;
 
 fn main() {
@@ -24,7 +26,7 @@ fn main() {
     let mut count = 0;
     for _ in 0..tries {
         let (ciphertext, is_cbc) = encryption_oracle(plaintext);
-        let cbc_detected = ciphertext[16..=31] != ciphertext[32..=47];
+        let cbc_detected = repeated_blocks(&ciphertext, 16).unwrap().iter().all(Option::is_none);
 
         if is_cbc == cbc_detected {
            count += 1;
@@ -38,15 +40,15 @@ fn encryption_oracle(data: &[u8]) -> (Vec<u8>, bool) {
     let mut rng = rand::thread_rng();
     let use_cbc: bool = rng.gen();
     
-    let key = random_bytes(16);
+    let key = Key::<16>::random();
     let prefix = random_bytes(rng.gen_range(5..=10));
     let suffix = random_bytes(rng.gen_range(5..=10));
 
     let salted = [prefix, data.to_vec(), suffix].concat();
 
     if use_cbc {
-        (aes_cbc_encrypt(&salted, &key, &[0; 16]), true)
+        (aes_cbc_encrypt(&salted, &key, &Iv::<16>::random(), &Pkcs7).unwrap(), true)
     } else {
-        (encrypt(Cipher::aes_128_ecb(), &key, None, &salted).unwrap(), false)
+        (aes_ecb_encrypt(&salted, &key, &Pkcs7).unwrap(), false)
     }
 }
@@ -1,5 +1,7 @@
+use cryptopals::crypto::{aes_ecb_decrypt, aes_ecb_encrypt};
+use cryptopals::key::Key;
+use cryptopals::padding::Pkcs7;
 use json::{object, JsonValue};
-use openssl::symm::{decrypt, encrypt, Cipher};
 
 fn main() {
     // We already know how to find the block size
@@ -58,9 +60,9 @@ fn main() {
 }
 
 // Random key
-const KEY: [u8; 16] = [
+static KEY: Key<16> = Key::from_array([
     222, 169, 210, 64, 54, 245, 202, 169, 10, 22, 227, 110, 176, 43, 11, 165,
-];
+]);
 
 fn kv_to_json(input: &str) -> Option<JsonValue> {
     let mut out = object! {};
@@ -81,11 +83,11 @@ fn profile_for(email: &str) -> String {
 
 fn profile_encrypt(email: &str) -> Vec<u8> {
     let plain = profile_for(email);
-    encrypt(Cipher::aes_128_ecb(), &KEY, None, &plain.as_bytes()).unwrap()
+    aes_ecb_encrypt(plain.as_bytes(), &KEY, &Pkcs7).unwrap()
 }
 
 fn profile_decrypt(cipher: &[u8]) -> Option<JsonValue> {
-    if let Ok(plain) = decrypt(Cipher::aes_128_ecb(), &KEY, None, cipher) {
+    if let Ok(plain) = aes_ecb_decrypt(cipher, &KEY, &Pkcs7) {
         kv_to_json(&String::from_utf8_lossy(&plain))
     } else {
         None
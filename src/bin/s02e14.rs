@@ -1,5 +1,7 @@
+use cryptopals::crypto::aes_ecb_encrypt;
+use cryptopals::padding::Pkcs7;
+use cryptopals::parallel::{Engine, Outcome};
 use cryptopals::tools::ToBytes;
-use openssl::symm::{encrypt, Cipher};
 
 fn main() {
     // Let's skip the discovery of the block size and the ECB detection
@@ -32,21 +34,25 @@ fn main() {
     let mut out = Vec::new();
     let b_start = ((initial_length)/block_size-1)*block_size;
     let b_end = b_start + block_size;
+    // Every byte value is tried in parallel, the search stops at
+    // the one giving the same block.
+    let engine = Engine::default();
+    let bytes: Vec<u8> = (u8::MIN..=u8::MAX).collect();
     for _ in 0..initial_length {
         padding.pop();
+        let c1 = encryption_oracle(&padding);
 
-        for b in u8::MIN..u8::MAX {
-            let c1 = encryption_oracle(&padding);
-
+        let found = engine.search(&bytes, |&b| {
             let mut candidate = padding.clone();
             candidate.extend(&out);
             candidate.push(b);
             let c2 = encryption_oracle(&candidate);
 
-            if c1[b_start..b_end] == c2[b_start..b_end] {
-                out.push(b);
-                break;
-            }
+            if c1[b_start..b_end] == c2[b_start..b_end] { Outcome::Found(b) } else { Outcome::Skip }
+        }, |a, _| a);
+
+        if let Some(b) = found {
+            out.push(b);
         }
     }
 
@@ -62,5 +68,5 @@ fn encryption_oracle(data: &[u8]) -> Vec<u8> {
                 + "YnkK").parse_base64().unwrap();
 
     let salted = [&prefix, data, &suffix].concat();
-    encrypt(Cipher::aes_128_ecb(), &key, None, &salted).unwrap()
+    aes_ecb_encrypt(&salted, &key, &Pkcs7).unwrap()
 }
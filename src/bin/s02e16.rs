@@ -1,35 +1,51 @@
-use cryptopals::crypto::aes_cbc_encrypt;
+use cryptopals::crypto::{aes_cbc_decrypt, aes_cbc_encrypt};
+use cryptopals::key::{Iv, Key};
+use cryptopals::padding::Pkcs7;
 
 fn main() {
-    // TODO
-    // Modify the ciphertext (without knowledge of the AES key) to accomplish this.
-    // You're relying on the fact that in CBC mode, a 1-bit error in a ciphertext block:
+    // In CBC mode each plaintext block is XORed with the previous
+    // ciphertext block after decryption: flipping a bit in a ciphertext
+    // block scrambles that block, but flips the same bit in the next
+    // plaintext block.
     //
-    //     Completely scrambles the block the error occurs in
-    //     Produces the identical 1-bit error(/edit) in the next ciphertext block.
-    //
-    // Stop and think for a second.
-    //
-    // Before you implement this attack, answer this question: why does CBC mode have this property?
+    // The prefix is two blocks long, so our input starts at the third
+    // one. The first block of input is sacrificed, and the second one
+    // holds ";admin=true;" with the forbidden chars replaced by their
+    // neighbours, one bit away.
+    let block_size = 16;
+    let input = "AAAAAAAAAAAAAAAA:admin<true:AAAA";
+    let mut ciphertext = encryption_oracle(input);
+
+    for (i, c) in input.bytes().enumerate().skip(block_size) {
+        if c == b':' || c == b'<' {
+            ciphertext[block_size + i] ^= 1;
+        }
+    }
+
+    if is_admin(&ciphertext) {
+        println!("Admin!");
+    } else {
+        println!("Not admin");
+    }
 }
 
 // Random key
-const KEY: [u8; 16] = [
+static KEY: Key<16> = Key::from_array([
     222, 169, 210, 64, 54, 245, 202, 169, 10, 22, 227, 110, 176, 43, 11, 165,
-];
+]);
 
-const IV: [u8; 16] = [0; 16];
+static IV: Iv<16> = Iv::from_array([0; 16]);
 
 fn encryption_oracle(input: &str) -> Vec<u8> {
     let salted = "comment1=cooking%20MCs;userdata=".to_string() 
     + &input.to_string().replace(";", "").replace("=", "")
     + ";comment2=%20like%20a%20pound%20of%20bacon";
 
-    aes_cbc_encrypt(salted.as_bytes(), &KEY, &IV)
+    aes_cbc_encrypt(salted.as_bytes(), &KEY, &IV, &Pkcs7).unwrap()
 }
 
 fn is_admin(bytes: &[u8]) -> bool {
-    let plain_bytes = aes_cbc_encrypt(bytes, &KEY, &IV);
+    let plain_bytes = aes_cbc_decrypt(bytes, &KEY, &IV, &Pkcs7).unwrap();
     let plain = String::from_utf8_lossy(&plain_bytes);
     
     plain.contains(";admin=true;")
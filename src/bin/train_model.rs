@@ -0,0 +1,34 @@
+use std::env;
+use std::fs;
+use std::process;
+use cryptopals::language::{LanguageModel, BUILTIN_LANGUAGES};
+
+// Trains a language model from a plaintext corpus:
+//   cargo run --bin train_model -- <name> <corpus> <output>
+// Without arguments, retrains the shipped models from res/corpus.
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    match &args[..] {
+        [] => {
+            for name in BUILTIN_LANGUAGES {
+                train(name, &format!("./res/corpus/{}.txt", name), &format!("./res/models/{}.model", name));
+            }
+        }
+        [name, corpus, output] => train(name, corpus, output),
+        _ => {
+            eprintln!("Usage: train_model [<name> <corpus> <output>]");
+            process::exit(1);
+        }
+    }
+}
+
+fn train(name: &str, corpus: &str, output: &str) {
+    let text = fs::read(corpus).unwrap_or_else(|e| {
+        eprintln!("Can't read {}: {}", corpus, e);
+        process::exit(1);
+    });
+
+    LanguageModel::train(name, &text).save(output).unwrap();
+    println!("{}: {} bytes -> {}", name, text.len(), output);
+}
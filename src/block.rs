@@ -0,0 +1,258 @@
+use std::ops::{BitXor, BitXorAssign, Deref, DerefMut};
+use crate::Error;
+
+/// A fixed-size block of bytes, as processed by block ciphers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(transparent)]
+pub struct Block<const N: usize>(pub [u8; N]);
+
+impl<const N: usize> Block<N> {
+    /// Returns a block of zeros
+    pub fn zero() -> Self {
+        Block([0; N])
+    }
+
+    /// Views an array as a block, without copying it
+    pub fn from_ref(bytes: &[u8; N]) -> &Self {
+        // Block is a transparent wrapper of [u8; N],
+        // so they have the same layout.
+        unsafe { &*(bytes as *const [u8; N] as *const Self) }
+    }
+
+    /// Views an array as a mutable block, without copying it
+    pub fn from_mut(bytes: &mut [u8; N]) -> &mut Self {
+        // See from_ref
+        unsafe { &mut *(bytes as *mut [u8; N] as *mut Self) }
+    }
+}
+
+impl<const N: usize> Default for Block<N> {
+    fn default() -> Self {
+        Self::zero()
+    }
+}
+
+impl<const N: usize> Deref for Block<N> {
+    type Target = [u8; N];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<const N: usize> DerefMut for Block<N> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<const N: usize> AsRef<[u8]> for Block<N> {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl<const N: usize> From<[u8; N]> for Block<N> {
+    fn from(bytes: [u8; N]) -> Self {
+        Block(bytes)
+    }
+}
+
+impl<const N: usize> From<Block<N>> for [u8; N] {
+    fn from(block: Block<N>) -> Self {
+        block.0
+    }
+}
+
+impl<const N: usize> From<Block<N>> for Vec<u8> {
+    fn from(block: Block<N>) -> Self {
+        block.0.to_vec()
+    }
+}
+
+impl<const N: usize> TryFrom<&[u8]> for Block<N> {
+    type Error = Error;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Error> {
+        <[u8; N]>::try_from(bytes)
+            .map(Block)
+            .map_err(|_| Error::InvalidLength)
+    }
+}
+
+impl<const N: usize> BitXorAssign<&Block<N>> for Block<N> {
+    fn bitxor_assign(&mut self, rhs: &Block<N>) {
+        for (a, b) in self.0.iter_mut().zip(rhs.0) {
+            *a ^= b;
+        }
+    }
+}
+
+impl<const N: usize> BitXorAssign for Block<N> {
+    fn bitxor_assign(&mut self, rhs: Block<N>) {
+        *self ^= &rhs;
+    }
+}
+
+impl<const N: usize> BitXor for Block<N> {
+    type Output = Block<N>;
+
+    fn bitxor(mut self, rhs: Block<N>) -> Block<N> {
+        self ^= &rhs;
+        self
+    }
+}
+
+impl<const N: usize> BitXor for &Block<N> {
+    type Output = Block<N>;
+
+    fn bitxor(self, rhs: &Block<N>) -> Block<N> {
+        let mut out = *self;
+        out ^= rhs;
+        out
+    }
+}
+
+/// Checks that a buffer can be split into whole blocks of size N
+fn check_blocks<const N: usize>(bytes: &[u8]) -> Result<(), Error> {
+    if N == 0 {
+        return Err(Error::InvalidBlockSize(N));
+    }
+
+    if !bytes.len().is_multiple_of(N) {
+        return Err(Error::InvalidLength);
+    }
+
+    Ok(())
+}
+
+/// Iterates over the blocks of a buffer. Fails if the
+/// length of the buffer is not a multiple of N.
+pub fn blocks<const N: usize>(bytes: &[u8]) -> Result<impl Iterator<Item = &Block<N>>, Error> {
+    check_blocks::<N>(bytes)?;
+
+    // The length is a multiple of N, so every chunk converts
+    Ok(bytes.chunks_exact(N).map(|c| Block::from_ref(c.try_into().unwrap())))
+}
+
+/// Iterates mutably over the blocks of a buffer. Fails if the
+/// length of the buffer is not a multiple of N.
+pub fn blocks_mut<const N: usize>(bytes: &mut [u8]) -> Result<impl Iterator<Item = &mut Block<N>>, Error> {
+    check_blocks::<N>(bytes)?;
+
+    // See blocks
+    Ok(bytes.chunks_exact_mut(N).map(|c| Block::from_mut(c.try_into().unwrap())))
+}
+
+/// Copies the blocks of a buffer into a vector
+pub fn split_blocks<const N: usize>(bytes: &[u8]) -> Result<Vec<Block<N>>, Error> {
+    Ok(blocks::<N>(bytes)?.copied().collect())
+}
+
+/// Concatenates blocks into a buffer
+pub fn join_blocks<const N: usize>(blocks: &[Block<N>]) -> Vec<u8> {
+    blocks.iter().flat_map(|b| b.0).collect()
+}
+
+/// Returns a reference to the i-th block of a buffer
+pub fn block_at<const N: usize>(bytes: &[u8], i: usize) -> Result<&Block<N>, Error> {
+    blocks::<N>(bytes)?.nth(i).ok_or(Error::InvalidLength)
+}
+
+/// Returns a mutable reference to the i-th block of a buffer
+pub fn block_at_mut<const N: usize>(bytes: &mut [u8], i: usize) -> Result<&mut Block<N>, Error> {
+    blocks_mut::<N>(bytes)?.nth(i).ok_or(Error::InvalidLength)
+}
+
+/// Swaps the i-th and j-th blocks of a buffer in place
+pub fn swap_blocks<const N: usize>(bytes: &mut [u8], i: usize, j: usize) -> Result<(), Error> {
+    check_blocks::<N>(bytes)?;
+
+    let n_blocks = bytes.len() / N;
+    if i >= n_blocks || j >= n_blocks {
+        return Err(Error::InvalidLength);
+    }
+
+    if i != j {
+        let (a, b) = (i.min(j), i.max(j));
+        let (left, right) = bytes.split_at_mut(b * N);
+        left[a * N..(a + 1) * N].swap_with_slice(&mut right[..N]);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn xor_works() {
+        let a = Block([0b1100, 0xFF]);
+        let b = Block([0b1010, 0x0F]);
+
+        assert_eq!(a ^ b, Block([0b0110, 0xF0]));
+        let (ra, rb) = (&a, &b);
+        assert_eq!(ra ^ rb, Block([0b0110, 0xF0]));
+
+        let mut c = a;
+        c ^= &b;
+        c ^= b;
+        assert_eq!(c, a);
+    }
+
+    #[test]
+    fn conversions_work() {
+        assert_eq!(Block::<4>::try_from(&b"abcd"[..]), Ok(Block(*b"abcd")));
+        assert_eq!(Block::<4>::try_from(&b"abc"[..]), Err(Error::InvalidLength));
+        assert_eq!(Vec::from(Block(*b"ab")), b"ab");
+        assert_eq!(<[u8; 2]>::from(Block(*b"ab")), *b"ab");
+        assert_eq!(Block::<3>::default(), Block([0; 3]));
+        assert_eq!(Block(*b"abc")[1], b'b');
+    }
+
+    #[test]
+    fn blocks_work() {
+        let bytes = b"aaaabbbbcccc";
+        let collected: Vec<&Block<4>> = blocks(bytes).unwrap().collect();
+        assert_eq!(collected, [&Block(*b"aaaa"), &Block(*b"bbbb"), &Block(*b"cccc")]);
+
+        assert!(blocks::<5>(bytes).is_err());
+        assert!(blocks::<0>(bytes).is_err());
+        assert_eq!(blocks::<4>(b"").unwrap().count(), 0);
+
+        let mut bytes = *b"aaaabbbb";
+        for b in blocks_mut::<4>(&mut bytes).unwrap() {
+            *b ^= Block([0x20; 4]);
+        }
+        assert_eq!(&bytes, b"AAAABBBB");
+        assert!(blocks_mut::<3>(&mut bytes).is_err());
+    }
+
+    #[test]
+    fn split_join_work() {
+        let bytes = b"aaaabbbbcccc";
+        let split = split_blocks::<4>(bytes).unwrap();
+
+        assert_eq!(split.len(), 3);
+        assert_eq!(join_blocks(&split), bytes);
+        assert_eq!(split_blocks::<5>(bytes), Err(Error::InvalidLength));
+    }
+
+    #[test]
+    fn block_access_works() {
+        let mut bytes = *b"aaaabbbbcccc";
+
+        assert_eq!(block_at::<4>(&bytes, 1), Ok(&Block(*b"bbbb")));
+        assert_eq!(block_at::<4>(&bytes, 3), Err(Error::InvalidLength));
+
+        *block_at_mut::<4>(&mut bytes, 2).unwrap() = Block(*b"dddd");
+        assert_eq!(&bytes, b"aaaabbbbdddd");
+
+        swap_blocks::<4>(&mut bytes, 2, 0).unwrap();
+        assert_eq!(&bytes, b"ddddbbbbaaaa");
+        swap_blocks::<4>(&mut bytes, 1, 1).unwrap();
+        assert_eq!(&bytes, b"ddddbbbbaaaa");
+        assert_eq!(swap_blocks::<4>(&mut bytes, 1, 3), Err(Error::InvalidLength));
+    }
+}
@@ -0,0 +1,171 @@
+#[cfg(feature = "openssl")]
+use openssl::symm::{Cipher, Crypter, Mode};
+use crate::aes::Aes;
+use crate::block::Block;
+use crate::Error;
+
+/// A block cipher, the primitive the modes of operation are built on.
+/// Blocks are passed as slices so that ciphers of any block size can
+/// be used: passing a block of the wrong size is an error.
+pub trait BlockCipher {
+    fn block_size(&self) -> usize;
+    fn encrypt_block(&self, block: &mut [u8]) -> Result<(), Error>;
+    fn decrypt_block(&self, block: &mut [u8]) -> Result<(), Error>;
+}
+
+impl<C: BlockCipher + ?Sized> BlockCipher for &C {
+    fn block_size(&self) -> usize {
+        (**self).block_size()
+    }
+
+    fn encrypt_block(&self, block: &mut [u8]) -> Result<(), Error> {
+        (**self).encrypt_block(block)
+    }
+
+    fn decrypt_block(&self, block: &mut [u8]) -> Result<(), Error> {
+        (**self).decrypt_block(block)
+    }
+}
+
+impl BlockCipher for Aes {
+    fn block_size(&self) -> usize {
+        Aes::BLOCK_SIZE
+    }
+
+    fn encrypt_block(&self, block: &mut [u8]) -> Result<(), Error> {
+        let block = <&mut [u8; 16]>::try_from(block).map_err(|_| Error::InvalidLength)?;
+        Aes::encrypt_block(self, Block::from_mut(block));
+        Ok(())
+    }
+
+    fn decrypt_block(&self, block: &mut [u8]) -> Result<(), Error> {
+        let block = <&mut [u8; 16]>::try_from(block).map_err(|_| Error::InvalidLength)?;
+        Aes::decrypt_block(self, Block::from_mut(block));
+        Ok(())
+    }
+}
+
+/// AES backed by openssl, with a 128, 192 or 256 bit key
+#[cfg(feature = "openssl")]
+#[derive(Clone)]
+pub struct OpensslAes {
+    cipher: Cipher,
+    key: Vec<u8>,
+}
+
+#[cfg(feature = "openssl")]
+impl OpensslAes {
+    pub fn new(key: &[u8]) -> Result<Self, Error> {
+        let cipher = match key.len() {
+            16 => Cipher::aes_128_ecb(),
+            24 => Cipher::aes_192_ecb(),
+            32 => Cipher::aes_256_ecb(),
+            n => return Err(Error::InvalidKeyLength(n)),
+        };
+
+        Ok(OpensslAes { cipher, key: key.to_vec() })
+    }
+
+    fn crypt(&self, mode: Mode, block: &mut [u8]) -> Result<(), Error> {
+        if block.len() != 16 {
+            return Err(Error::InvalidLength);
+        }
+
+        let mut crypter = Crypter::new(self.cipher, mode, &self.key, None)?;
+        // Without this, openssl would hold back the (only) block
+        // waiting for the final padded one.
+        crypter.pad(false);
+
+        let mut buffer = [0; 32];
+        crypter.update(block, &mut buffer)?;
+        block.copy_from_slice(&buffer[..16]);
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "openssl")]
+impl BlockCipher for OpensslAes {
+    fn block_size(&self) -> usize {
+        16
+    }
+
+    fn encrypt_block(&self, block: &mut [u8]) -> Result<(), Error> {
+        self.crypt(Mode::Encrypt, block)
+    }
+
+    fn decrypt_block(&self, block: &mut [u8]) -> Result<(), Error> {
+        self.crypt(Mode::Decrypt, block)
+    }
+}
+
+/// The AES used by the `aes_*` shorthands: openssl's if the feature
+/// is enabled, ours otherwise
+#[cfg(feature = "openssl")]
+pub(crate) type DefaultAes = OpensslAes;
+#[cfg(not(feature = "openssl"))]
+pub(crate) type DefaultAes = Aes;
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// A toy cipher with 8-byte blocks: XOR with the key, then
+    /// rotation by one byte. Only meant to test the modes.
+    pub struct Toy(pub [u8; 8]);
+
+    impl BlockCipher for Toy {
+        fn block_size(&self) -> usize {
+            8
+        }
+
+        fn encrypt_block(&self, block: &mut [u8]) -> Result<(), Error> {
+            if block.len() != 8 {
+                return Err(Error::InvalidLength);
+            }
+            block.iter_mut().zip(self.0).for_each(|(b, k)| *b ^= k);
+            block.rotate_left(1);
+            Ok(())
+        }
+
+        fn decrypt_block(&self, block: &mut [u8]) -> Result<(), Error> {
+            if block.len() != 8 {
+                return Err(Error::InvalidLength);
+            }
+            block.rotate_right(1);
+            block.iter_mut().zip(self.0).for_each(|(b, k)| *b ^= k);
+            Ok(())
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "openssl")]
+    fn aes_implementations_agree() {
+        use crate::crypto::random_bytes;
+
+        for key_size in [16, 24, 32] {
+            let key = random_bytes(key_size);
+            let ours = Aes::new(&key).unwrap();
+            let theirs = OpensslAes::new(&key).unwrap();
+
+            let mut a = random_bytes(16);
+            let mut b = a.clone();
+            BlockCipher::encrypt_block(&ours, &mut a).unwrap();
+            theirs.encrypt_block(&mut b).unwrap();
+            assert_eq!(a, b);
+
+            theirs.decrypt_block(&mut a).unwrap();
+            BlockCipher::decrypt_block(&ours, &mut b).unwrap();
+            assert_eq!(a, b);
+        }
+    }
+
+    #[test]
+    fn bad_blocks_are_rejected() {
+        let aes = Aes::new(&[0; 16]).unwrap();
+        assert_eq!(BlockCipher::encrypt_block(&aes, &mut [0; 15]), Err(Error::InvalidLength));
+        assert_eq!(BlockCipher::decrypt_block(&DefaultAes::new(&[0; 16]).unwrap(), &mut [0; 17]), Err(Error::InvalidLength));
+        assert!(matches!(DefaultAes::new(&[0; 8]), Err(Error::InvalidKeyLength(8))));
+        assert_eq!(Toy([0; 8]).encrypt_block(&mut [0; 16]), Err(Error::InvalidLength));
+    }
+}
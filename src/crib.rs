@@ -0,0 +1,335 @@
+use std::io::{BufRead, Write};
+use crate::language::Scorer;
+use crate::Error;
+
+/// A position where a crib gives plausible plaintext in the other
+/// ciphertexts
+#[derive(Debug, Clone, PartialEq)]
+pub struct CribMatch {
+    /// Ciphertext assumed to contain the crib
+    pub index: usize,
+    pub offset: usize,
+    /// Keystream implied by the crib at this position
+    pub keystream: Vec<u8>,
+    /// Fragments of the other plaintexts, with their ciphertext index
+    pub fragments: Vec<(usize, Vec<u8>)>,
+    /// Average score of the fragments
+    pub score: f64,
+}
+
+/// Recovers the plaintexts of several ciphertexts encrypted under
+/// the same keystream (a many-time pad) by crib dragging: guessing
+/// a word in one of them reveals the keystream at its position,
+/// and so fragments of all the others.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CribDragger {
+    ciphertexts: Vec<Vec<u8>>,
+    keystream: Vec<Option<u8>>,
+    confidence: Vec<Option<f64>>,
+}
+
+/// Confidence of a guess made by hand (e.g. with `set`), above any
+/// score a scorer can give
+pub const MANUAL_CONFIDENCE: f64 = f64::INFINITY;
+
+impl CribDragger {
+    pub fn new(ciphertexts: Vec<Vec<u8>>) -> Self {
+        let len = ciphertexts.iter().map(Vec::len).max().unwrap_or(0);
+        CribDragger { ciphertexts, keystream: vec![None; len], confidence: vec![None; len] }
+    }
+
+    pub fn ciphertexts(&self) -> &[Vec<u8>] {
+        &self.ciphertexts
+    }
+
+    /// The keystream recovered so far, None where it is unknown
+    pub fn keystream(&self) -> &[Option<u8>] {
+        &self.keystream
+    }
+
+    /// Score of the guess each keystream byte comes from, None where
+    /// the keystream is unknown
+    pub fn confidence(&self) -> &[Option<f64>] {
+        &self.confidence
+    }
+
+    /// Slides the crib across every ciphertext, XORing the implied
+    /// keystream with each of the others, and returns the `top`
+    /// positions whose fragments the scorer likes most.
+    pub fn drag<S: Scorer + ?Sized>(&self, crib: &[u8], scorer: &S, top: usize) -> Vec<CribMatch> {
+        let mut matches = Vec::new();
+
+        for (index, ciphertext) in self.ciphertexts.iter().enumerate() {
+            if crib.is_empty() || ciphertext.len() < crib.len() {
+                continue;
+            }
+
+            for offset in 0..=ciphertext.len() - crib.len() {
+                let keystream: Vec<u8> = ciphertext[offset..].iter().zip(crib).map(|(c, p)| c ^ p).collect();
+                let fragments: Vec<(usize, Vec<u8>)> = self.ciphertexts.iter()
+                    .enumerate()
+                    .filter(|&(j, other)| j != index && other.len() > offset)
+                    .map(|(j, other)| (j, other[offset..].iter().zip(&keystream).map(|(c, k)| c ^ k).collect()))
+                    .collect();
+
+                // Nothing to check the guess against
+                if fragments.is_empty() {
+                    continue;
+                }
+
+                let score = fragments.iter().map(|(_, f)| scorer.score(f)).sum::<f64>() / fragments.len() as f64;
+                matches.push(CribMatch { index, offset, keystream, fragments, score });
+            }
+        }
+
+        matches.sort_by(|a, b| b.score.total_cmp(&a.score));
+        matches.truncate(top);
+        matches
+    }
+
+    /// Assumes that the plaintext of a ciphertext contains `text` at
+    /// `offset`, and updates the keystream accordingly. Fails if the
+    /// text does not fit in the ciphertext.
+    pub fn guess(&mut self, index: usize, offset: usize, text: &[u8], confidence: f64) -> Result<(), Error> {
+        let ciphertext = self.ciphertexts.get(index).ok_or(Error::InvalidLength)?;
+        let end = offset.checked_add(text.len()).filter(|&end| end <= ciphertext.len()).ok_or(Error::InvalidLength)?;
+
+        for (i, p) in (offset..end).zip(text) {
+            self.keystream[i] = Some(ciphertext[i] ^ p);
+            self.confidence[i] = Some(confidence);
+        }
+
+        Ok(())
+    }
+
+    /// Accepts a match returned by `drag`. Keystream bytes from a
+    /// guess with a higher confidence (e.g. a manual one) are kept.
+    /// Fails if the match does not fit in its ciphertext.
+    pub fn accept(&mut self, m: &CribMatch) -> Result<(), Error> {
+        let ciphertext = self.ciphertexts.get(m.index).ok_or(Error::InvalidLength)?;
+        let end = m.offset.checked_add(m.keystream.len()).filter(|&end| end <= ciphertext.len()).ok_or(Error::InvalidLength)?;
+
+        for (i, k) in (m.offset..end).zip(&m.keystream) {
+            if self.confidence[i].is_none_or(|c| m.score >= c) {
+                self.keystream[i] = Some(*k);
+                self.confidence[i] = Some(m.score);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Forgets the keystream in a range, e.g. after a wrong guess
+    pub fn clear(&mut self, offset: usize, len: usize) {
+        let end = offset.saturating_add(len).min(self.keystream.len());
+        let start = offset.min(end);
+        self.keystream[start..end].fill(None);
+        self.confidence[start..end].fill(None);
+    }
+
+    /// Decrypts the ciphertexts with the known keystream
+    pub fn plaintexts(&self) -> Vec<Vec<Option<u8>>> {
+        self.ciphertexts.iter()
+            .map(|c| c.iter().zip(&self.keystream).map(|(c, k)| k.map(|k| c ^ k)).collect())
+            .collect()
+    }
+
+    /// The plaintexts as text: unknown bytes are shown as '_'
+    /// and non-printable ones as '?'
+    pub fn render(&self) -> Vec<String> {
+        self.plaintexts().iter()
+            .map(|p| {
+                p.iter()
+                    .map(|b| match b {
+                        None => '_',
+                        Some(b) if b.is_ascii_graphic() || *b == b' ' => *b as char,
+                        Some(_) => '?',
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Runs an interactive session reading commands from `input`
+    /// and writing to `output`, until `quit` or the end of the input.
+    /// The plaintexts are shown again after every change.
+    pub fn interactive<S, R, W>(&mut self, scorer: &S, input: R, mut output: W) -> Result<(), Error>
+    where
+        S: Scorer + ?Sized,
+        R: BufRead,
+        W: Write,
+    {
+        let mut matches: Vec<CribMatch> = Vec::new();
+        writeln!(output, "{}", HELP)?;
+        self.show(&mut output)?;
+
+        for line in input.lines() {
+            let line = line?;
+            let (command, arg) = line.split_once(' ').unwrap_or((&line, ""));
+
+            match command {
+                "drag" if !arg.is_empty() => {
+                    matches = self.drag(arg.as_bytes(), scorer, 10);
+                    for (n, m) in matches.iter().enumerate() {
+                        writeln!(output, "{:2}) #{} @{} ({:.2})", n, m.index, m.offset, m.score)?;
+                        for (j, f) in &m.fragments {
+                            writeln!(output, "      #{}: {:?}", j, String::from_utf8_lossy(f))?;
+                        }
+                    }
+                }
+                "accept" | "reject" => {
+                    let n = match arg.parse::<usize>() {
+                        Ok(n) if n < matches.len() => n,
+                        _ => {
+                            writeln!(output, "No such match")?;
+                            continue;
+                        }
+                    };
+                    let m = matches.remove(n);
+                    if command == "accept" {
+                        match self.accept(&m) {
+                            Ok(()) => self.show(&mut output)?,
+                            Err(_) => writeln!(output, "No such match")?,
+                        }
+                    }
+                }
+                "set" => {
+                    let mut args = arg.splitn(3, ' ');
+                    let index = args.next().and_then(|a| a.parse().ok());
+                    let offset = args.next().and_then(|a| a.parse().ok());
+                    let text = args.next().unwrap_or("");
+                    match (index, offset) {
+                        (Some(i), Some(o)) if self.guess(i, o, text.as_bytes(), MANUAL_CONFIDENCE).is_ok() => self.show(&mut output)?,
+                        _ => writeln!(output, "Usage: set <ciphertext> <offset> <text>")?,
+                    }
+                }
+                "clear" => {
+                    let args: Vec<usize> = arg.split(' ').filter_map(|a| a.parse().ok()).collect();
+                    match args[..] {
+                        [offset, len] => {
+                            self.clear(offset, len);
+                            self.show(&mut output)?;
+                        }
+                        _ => writeln!(output, "Usage: clear <offset> <length>")?,
+                    }
+                }
+                "show" => self.show(&mut output)?,
+                "quit" => break,
+                "" => {}
+                _ => writeln!(output, "{}", HELP)?,
+            }
+        }
+
+        Ok(())
+    }
+
+    fn show<W: Write>(&self, output: &mut W) -> Result<(), Error> {
+        for (i, p) in self.render().iter().enumerate() {
+            writeln!(output, "{:3}: {}", i, p)?;
+        }
+        Ok(())
+    }
+}
+
+const HELP: &str = "Commands: drag <crib> | accept <n> | reject <n> | set <ciphertext> <offset> <text> | clear <offset> <length> | show | quit";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::xor_string;
+    use crate::language::LanguageModel;
+
+    const PLAINTEXTS: [&[u8]; 4] = [
+        b"the meeting is moved to the old station",
+        b"bring the money and the documents",
+        b"nobody must know that the plan changed",
+        b"we will wait for you at the river",
+    ];
+    const KEYSTREAM: &[u8] = b"\x8a\x13\xf0\x42\x9c\x01\x7e\x55\xd3\x28\x64\xb9\x0f\xe1\x36\x7a\xc4\x99\x20\x5b\x03\xee\x71\x4d\xa8\x16\xfb\x62\x39\xd0\x8e\x07\x54\xcb\x2f\x90\x6d\x11\xb3\x48";
+
+    fn dragger() -> CribDragger {
+        CribDragger::new(PLAINTEXTS.iter().map(|p| xor_string(p, KEYSTREAM)).collect())
+    }
+
+    #[test]
+    fn drag_finds_cribs() {
+        let model = LanguageModel::english();
+        let dragger = dragger();
+        let matches = dragger.drag(b" the ", &model, 10);
+
+        assert_eq!(matches.len(), 10);
+        assert!(matches.iter().any(|m| m.index == 0 && m.offset == 23));
+        assert!(matches[0].score >= matches[1].score);
+
+        let m = &matches[0];
+        assert_eq!(m.keystream, &KEYSTREAM[m.offset..m.offset + 5]);
+        assert!(dragger.drag(b"", &model, 10).is_empty());
+    }
+
+    #[test]
+    fn guesses_update_the_plaintexts() {
+        let mut dragger = dragger();
+        assert_eq!(dragger.render()[0], "_".repeat(39));
+
+        dragger.guess(1, 0, b"bring", 1.5).unwrap();
+        assert_eq!(dragger.render()[0], format!("the m{}", "_".repeat(34)));
+        assert_eq!(dragger.render()[3], format!("we wi{}", "_".repeat(28)));
+        assert_eq!(dragger.keystream()[..5], KEYSTREAM[..5].iter().map(|&k| Some(k)).collect::<Vec<_>>());
+        assert_eq!(dragger.confidence()[4], Some(1.5));
+
+        dragger.clear(3, 100);
+        assert_eq!(dragger.render()[2], format!("nob{}", "_".repeat(35)));
+        assert_eq!(dragger.confidence()[3], None);
+        assert_eq!(dragger.guess(1, 30, b"abcd", 0.0), Err(Error::InvalidLength));
+        assert_eq!(dragger.guess(4, 0, b"a", 0.0), Err(Error::InvalidLength));
+        assert_eq!(dragger.guess(1, usize::MAX, b"ab", 0.0), Err(Error::InvalidLength));
+
+        dragger.clear(usize::MAX, 2);
+        assert_eq!(dragger.render()[2], format!("nob{}", "_".repeat(35)));
+    }
+
+    #[test]
+    fn accept_keeps_better_guesses() {
+        let mut dragger = dragger();
+        dragger.guess(1, 0, b"bring", MANUAL_CONFIDENCE).unwrap();
+
+        let m = CribMatch { index: 0, offset: 3, keystream: vec![0; 4], fragments: Vec::new(), score: 2.0 };
+        dragger.accept(&m).unwrap();
+        assert_eq!(dragger.keystream()[..5], KEYSTREAM[..5].iter().map(|&k| Some(k)).collect::<Vec<_>>());
+        assert_eq!(dragger.keystream()[5..7], [Some(0), Some(0)]);
+        assert_eq!(dragger.confidence()[6], Some(2.0));
+
+        dragger.accept(&CribMatch { score: 1.0, offset: 6, ..m.clone() }).unwrap();
+        assert_eq!(dragger.confidence()[6], Some(2.0));
+        assert_eq!(dragger.confidence()[8], Some(1.0));
+
+        assert_eq!(dragger.accept(&CribMatch { offset: 38, ..m.clone() }), Err(Error::InvalidLength));
+        assert_eq!(dragger.accept(&CribMatch { offset: usize::MAX, ..m.clone() }), Err(Error::InvalidLength));
+        assert_eq!(dragger.accept(&CribMatch { index: 4, ..m }), Err(Error::InvalidLength));
+    }
+
+    #[test]
+    fn interactive_works() {
+        let model = LanguageModel::english();
+        let mut dragger = dragger();
+        let input = b"drag the old station\nreject 7\nset 3 0 we will wait\nbogus\naccept 99\nquit\nshow\n";
+        let mut output = Vec::new();
+
+        dragger.interactive(&model, &input[..], &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        assert!(output.contains(" 0) #0 @24"));
+        assert!(output.contains("  1: bring the m"));
+        assert!(output.contains("No such match"));
+        assert!(output.matches(HELP).count() == 2);
+        assert_eq!(dragger.render()[2], format!("nobody must {}", "_".repeat(26)));
+        assert_eq!(dragger.confidence()[0], Some(MANUAL_CONFIDENCE));
+
+        let input = b"clear 18446744073709551615 2
+set 0 18446744073709551615 ab
+";
+        let mut output = Vec::new();
+        dragger.interactive(&model, &input[..], &mut output).unwrap();
+        assert!(String::from_utf8(output).unwrap().contains("Usage: set"));
+    }
+}
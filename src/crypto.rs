@@ -1,16 +1,21 @@
-use openssl::{symm::{Crypter, Cipher, Mode, encrypt}};
+#[cfg(feature = "openssl")]
+use openssl::symm::{Cipher, Crypter, Mode};
 use rand::Rng;
+use crate::cipher::{BlockCipher, DefaultAes};
+#[cfg(not(feature = "openssl"))]
+use crate::padding::NoPadding;
+use crate::padding::{Padding, Pkcs7};
+use crate::Error;
 
 /// Performs a XOR of a byte sequence on a single char key.
 /// Outputs a String
 pub fn xor_char(bytes: &[u8], c: u8) -> String {
-    let mut result = Vec::with_capacity(bytes.len());
-
-    for i in 0..bytes.len() {
-        result.push(bytes[i] ^ c);
-    }
+    String::from_utf8_lossy(&xor_byte(bytes, c)).to_string()
+}
 
-   String::from_utf8_lossy(&result).to_string()
+/// Performs a XOR of a byte sequence on a single byte key
+pub fn xor_byte(bytes: &[u8], key: u8) -> Vec<u8> {
+    bytes.iter().map(|b| b ^ key).collect()
 }
 
 /// Performs a byte by byte XOR of a byte sequence on a 
@@ -29,97 +34,340 @@ pub fn xor_string(s: &[u8], k: &[u8]) -> Vec<u8> {
 }
 
 /// Pads byte sequences according to PKCS#7
-pub fn pkcs7_pad(bytes: &[u8], block_size: u8) -> Vec<u8> {
-    let block_size = block_size as usize;
-    let r = block_size - (bytes.len() % block_size);
+pub fn pkcs7_pad(bytes: &[u8], block_size: u8) -> Result<Vec<u8>, Error> {
     let mut result = bytes.to_vec();
+    Pkcs7.pad(&mut result, block_size as usize)?;
+    Ok(result)
+}
+
+/// Unpads byte sequences according to PKCS#7
+pub fn pkcs7_unpad(bytes: &[u8], block_size: u8) -> Result<Vec<u8>, Error> {
+    Pkcs7.unpad(bytes, block_size as usize)
+}
+
+/// Block size of a cipher, which the modes can't use if zero
+pub(crate) fn checked_block_size<C: BlockCipher + ?Sized>(cipher: &C) -> Result<usize, Error> {
+    match cipher.block_size() {
+        0 => Err(Error::InvalidBlockSize(0)),
+        n => Ok(n),
+    }
+}
+
+/// Encrypts in ECB mode, after padding. With `NoPadding`, fails if
+/// the input is not a multiple of the block size.
+pub fn ecb_encrypt<C, P>(cipher: &C, bytes: &[u8], padding: &P) -> Result<Vec<u8>, Error>
+where
+    C: BlockCipher + ?Sized,
+    P: Padding + ?Sized,
+{
+    let block_size = checked_block_size(cipher)?;
+    let mut output = bytes.to_vec();
+    padding.pad(&mut output, block_size)?;
+
+    for block in output.chunks_exact_mut(block_size) {
+        cipher.encrypt_block(block)?;
+    }
+
+    Ok(output)
+}
+
+/// Decrypts in ECB mode, and removes the padding. Fails if the input
+/// is not a multiple of the block size.
+pub fn ecb_decrypt<C, P>(cipher: &C, bytes: &[u8], padding: &P) -> Result<Vec<u8>, Error>
+where
+    C: BlockCipher + ?Sized,
+    P: Padding + ?Sized,
+{
+    let block_size = checked_block_size(cipher)?;
+    if !bytes.len().is_multiple_of(block_size) {
+        return Err(Error::InvalidLength);
+    }
 
-    for _ in 0..r {
-        result.push(r as u8);
+    let mut output = bytes.to_vec();
+    for block in output.chunks_exact_mut(block_size) {
+        cipher.decrypt_block(block)?;
     }
 
-    result
+    output.truncate(padding.unpadded_len(&output, block_size)?);
+    Ok(output)
 }
 
-#[derive(Debug, PartialEq)]
-pub enum PaddingError{
-    InvalidLength,
-    InvalidPadding
+/// AES-ECB encryption with a 128, 192 or 256 bit key, see `ecb_encrypt`
+pub fn aes_ecb_encrypt<P: Padding + ?Sized>(bytes: &[u8], key: &[u8], padding: &P) -> Result<Vec<u8>, Error> {
+    ecb_encrypt(&DefaultAes::new(key)?, bytes, padding)
 }
 
-/// Unpads byte sequences according to PKCS#7
-pub fn pkcs7_unpad(bytes: &[u8], block_size: u8) -> Result<Vec<u8>, PaddingError> {
-    if bytes.len() % block_size as usize != 0 || bytes.len() == 0 {
-        return Err(PaddingError::InvalidLength);
+/// AES-ECB decryption with a 128, 192 or 256 bit key, see `ecb_decrypt`
+pub fn aes_ecb_decrypt<P: Padding + ?Sized>(bytes: &[u8], key: &[u8], padding: &P) -> Result<Vec<u8>, Error> {
+    ecb_decrypt(&DefaultAes::new(key)?, bytes, padding)
+}
+
+/// Checks that the IV is as long as a block, and that the ciphertext
+/// is a whole number of blocks
+fn check_chained_params<C: BlockCipher + ?Sized>(cipher: &C, bytes: Option<&[u8]>, iv: &[u8]) -> Result<usize, Error> {
+    let block_size = checked_block_size(cipher)?;
+    if iv.len() != block_size {
+        return Err(Error::InvalidIvLength(iv.len()));
+    }
+    if bytes.is_some_and(|b| !b.len().is_multiple_of(block_size)) {
+        return Err(Error::InvalidLength);
     }
+    Ok(block_size)
+}
+
+/// Encrypts in CBC mode, after padding. The IV must be as long as a
+/// block.
+pub fn cbc_encrypt<C, P>(cipher: &C, bytes: &[u8], iv: &[u8], padding: &P) -> Result<Vec<u8>, Error>
+where
+    C: BlockCipher + ?Sized,
+    P: Padding + ?Sized,
+{
+    let block_size = check_chained_params(cipher, None, iv)?;
+    let mut output = bytes.to_vec();
+    padding.pad(&mut output, block_size)?;
+    let mut prev = iv;
 
-    let last = bytes[bytes.len()-1];
-    if bytes.len() < last as usize {
-        return Err(PaddingError::InvalidPadding);
+    for block in output.chunks_exact_mut(block_size) {
+        block.iter_mut().zip(prev).for_each(|(b, p)| *b ^= p);
+        cipher.encrypt_block(block)?;
+        prev = block;
     }
 
+    Ok(output)
+}
+
+/// Decrypts in CBC mode, then removes the padding
+pub fn cbc_decrypt<C, P>(cipher: &C, bytes: &[u8], iv: &[u8], padding: &P) -> Result<Vec<u8>, Error>
+where
+    C: BlockCipher + ?Sized,
+    P: Padding + ?Sized,
+{
+    let block_size = check_chained_params(cipher, Some(bytes), iv)?;
     let mut output = bytes.to_vec();
-    let last_elements = &bytes[(bytes.len()-last as usize)..];
+    let prevs = iv.chunks_exact(block_size).chain(bytes.chunks_exact(block_size));
 
-    if last_elements == vec![last; last as usize] {
-        output.truncate(bytes.len()-last as usize);
-    } else {
-        return Err(PaddingError::InvalidPadding);
+    for (block, prev) in output.chunks_exact_mut(block_size).zip(prevs) {
+        cipher.decrypt_block(block)?;
+        block.iter_mut().zip(prev).for_each(|(b, p)| *b ^= p);
+    }
+
+    output.truncate(padding.unpadded_len(&output, block_size)?);
+    Ok(output)
+}
+
+/// Encrypts in PCBC mode: like CBC, but each block is XORed with
+/// both the previous plaintext and ciphertext, so that an error
+/// corrupts all the following blocks. The IV must be as long as a block.
+pub fn pcbc_encrypt<C, P>(cipher: &C, bytes: &[u8], iv: &[u8], padding: &P) -> Result<Vec<u8>, Error>
+where
+    C: BlockCipher + ?Sized,
+    P: Padding + ?Sized,
+{
+    let block_size = check_chained_params(cipher, None, iv)?;
+    let mut output = bytes.to_vec();
+    padding.pad(&mut output, block_size)?;
+    let mut prev = iv.to_vec();
+
+    for block in output.chunks_exact_mut(block_size) {
+        let plain = block.to_vec();
+        block.iter_mut().zip(&prev).for_each(|(b, p)| *b ^= p);
+        cipher.encrypt_block(block)?;
+        prev.iter_mut().zip(plain.iter().zip(block.iter())).for_each(|(p, (a, b))| *p = a ^ b);
+    }
+
+    Ok(output)
+}
+
+/// Decrypts in PCBC mode, then removes the padding
+pub fn pcbc_decrypt<C, P>(cipher: &C, bytes: &[u8], iv: &[u8], padding: &P) -> Result<Vec<u8>, Error>
+where
+    C: BlockCipher + ?Sized,
+    P: Padding + ?Sized,
+{
+    let block_size = check_chained_params(cipher, Some(bytes), iv)?;
+    let mut output = bytes.to_vec();
+    let mut prev = iv.to_vec();
+
+    for (block, encrypted) in output.chunks_exact_mut(block_size).zip(bytes.chunks_exact(block_size)) {
+        cipher.decrypt_block(block)?;
+        block.iter_mut().zip(&prev).for_each(|(b, p)| *b ^= p);
+        prev.iter_mut().zip(block.iter().zip(encrypted)).for_each(|(p, (a, b))| *p = a ^ b);
+    }
+
+    output.truncate(padding.unpadded_len(&output, block_size)?);
+    Ok(output)
+}
+
+/// Size of the segments encrypted at each step of CFB mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CfbSegment {
+    /// One byte (CFB-8)
+    Byte,
+    /// A whole block (CFB-128 for AES)
+    Block,
+}
+
+/// CFB mode: the keystream for each segment is the encryption of
+/// the last block of ciphertext (starting from the IV)
+fn cfb<C: BlockCipher + ?Sized>(cipher: &C, bytes: &[u8], iv: &[u8], segment: CfbSegment, decrypting: bool) -> Result<Vec<u8>, Error> {
+    if iv.len() != checked_block_size(cipher)? {
+        return Err(Error::InvalidIvLength(iv.len()));
+    }
+
+    let segment_size = match segment {
+        CfbSegment::Byte => 1,
+        CfbSegment::Block => iv.len(),
+    };
+    let mut output = bytes.to_vec();
+    let mut register = iv.to_vec();
+    let mut keystream = vec![0; iv.len()];
+
+    for chunk in output.chunks_mut(segment_size) {
+        keystream.copy_from_slice(&register);
+        cipher.encrypt_block(&mut keystream)?;
+
+        // The register is shifted, and fed with the ciphertext
+        register.drain(..chunk.len());
+        if decrypting {
+            register.extend_from_slice(chunk);
+        }
+        chunk.iter_mut().zip(&keystream).for_each(|(b, k)| *b ^= k);
+        if !decrypting {
+            register.extend_from_slice(chunk);
+        }
+    }
+
+    Ok(output)
+}
+
+/// Encrypts in CFB mode. No padding is needed; the IV must be as
+/// long as a block.
+pub fn cfb_encrypt<C: BlockCipher + ?Sized>(cipher: &C, bytes: &[u8], iv: &[u8], segment: CfbSegment) -> Result<Vec<u8>, Error> {
+    cfb(cipher, bytes, iv, segment, false)
+}
+
+/// Decrypts in CFB mode
+pub fn cfb_decrypt<C: BlockCipher + ?Sized>(cipher: &C, bytes: &[u8], iv: &[u8], segment: CfbSegment) -> Result<Vec<u8>, Error> {
+    cfb(cipher, bytes, iv, segment, true)
+}
+
+/// Encrypts in OFB mode: the keystream is made by encrypting the IV
+/// over and over. No padding is needed; the IV must be as long as a
+/// block.
+pub fn ofb_encrypt<C: BlockCipher + ?Sized>(cipher: &C, bytes: &[u8], iv: &[u8]) -> Result<Vec<u8>, Error> {
+    if iv.len() != checked_block_size(cipher)? {
+        return Err(Error::InvalidIvLength(iv.len()));
+    }
+
+    let mut output = bytes.to_vec();
+    let mut keystream = iv.to_vec();
+
+    for chunk in output.chunks_mut(iv.len()) {
+        cipher.encrypt_block(&mut keystream)?;
+        chunk.iter_mut().zip(&keystream).for_each(|(b, k)| *b ^= k);
     }
 
-    output.truncate(bytes.len()-last as usize);
     Ok(output)
 }
 
-/// Implements encryption AES-128-CBC by using ECB mode
-pub fn aes_cbc_encrypt(bytes: &[u8], key: &[u8], iv: &[u8]) -> Vec<u8> {
-    let cipher = Cipher::aes_128_ecb();
-    let block_size =cipher.block_size();
-    let mut output = Vec::with_capacity(bytes.len()+32);
-    let mut iv = iv.to_vec();
-    let bytes = pkcs7_pad(bytes, block_size as u8);
+/// Decrypts in OFB mode, which is the same as encrypting
+pub fn ofb_decrypt<C: BlockCipher + ?Sized>(cipher: &C, bytes: &[u8], iv: &[u8]) -> Result<Vec<u8>, Error> {
+    ofb_encrypt(cipher, bytes, iv)
+}
+
+/// Checks that key and IV have the sizes required by AES-CBC, before
+/// anything is encrypted
+fn check_aes_cbc_params(key: &[u8], iv: &[u8]) -> Result<(), Error> {
+    if ![16, 24, 32].contains(&key.len()) {
+        return Err(Error::InvalidKeyLength(key.len()));
+    }
+
+    if iv.len() != 16 {
+        return Err(Error::InvalidIvLength(iv.len()));
+    }
 
-    for i in (0..bytes.len()).step_by(block_size) {
-        let slice = if i+block_size+1 < bytes.len() { 
-            &bytes[i..(i+block_size)] 
-        } else {
-            &bytes[i..] 
-        };
+    Ok(())
+}
 
-        let xored = xor_string(slice, &iv);
-        let ciphertext = encrypt(cipher, key, Some(&iv), &xored).unwrap();
-        iv = ciphertext[..16].to_vec().clone();
-        
-        output.extend_from_slice(&ciphertext[..16]); 
+/// Size of the chunks processed at once by `aes_cbc_in_place`
+#[cfg(feature = "openssl")]
+const CBC_CHUNK: usize = 4096;
+
+/// Encrypts or decrypts with AES-CBC, in place, a whole number of
+/// blocks. A single openssl context is used, so the key schedule is
+/// computed only once.
+#[cfg(feature = "openssl")]
+fn aes_cbc_in_place(decrypting: bool, key: &[u8], iv: &[u8], data: &mut [u8]) -> Result<(), Error> {
+    let mode = if decrypting { Mode::Decrypt } else { Mode::Encrypt };
+    let cipher = match key.len() {
+        16 => Cipher::aes_128_cbc(),
+        24 => Cipher::aes_192_cbc(),
+        32 => Cipher::aes_256_cbc(),
+        n => return Err(Error::InvalidKeyLength(n)),
+    };
+
+    let mut crypter = Crypter::new(cipher, mode, key, Some(iv))?;
+    crypter.pad(false);
+
+    // openssl needs a separate output buffer, with room for an extra
+    // block. Without padding, whole blocks are output right away.
+    let mut buffer = [0; CBC_CHUNK + 16];
+    for chunk in data.chunks_mut(CBC_CHUNK) {
+        let n = crypter.update(chunk, &mut buffer)?;
+        chunk.copy_from_slice(&buffer[..n]);
     }
 
-    output
+    Ok(())
+}
+
+/// Encrypts or decrypts with AES-CBC, in place, a whole number of
+/// blocks, using our AES
+#[cfg(not(feature = "openssl"))]
+fn aes_cbc_in_place(decrypting: bool, key: &[u8], iv: &[u8], data: &mut [u8]) -> Result<(), Error> {
+    let aes = DefaultAes::new(key)?;
+    let output = if decrypting {
+        cbc_decrypt(&aes, data, iv, &NoPadding)?
+    } else {
+        cbc_encrypt(&aes, data, iv, &NoPadding)?
+    };
+
+    data.copy_from_slice(&output);
+    Ok(())
+}
+
+/// AES-CBC encryption with a 128, 192 or 256 bit key, chosen by the
+/// length of `key`, after padding
+pub fn aes_cbc_encrypt<P: Padding + ?Sized>(bytes: &[u8], key: &[u8], iv: &[u8], padding: &P) -> Result<Vec<u8>, Error> {
+    check_aes_cbc_params(key, iv)?;
+    let mut output = bytes.to_vec();
+    padding.pad(&mut output, 16)?;
+    aes_cbc_in_place(false, key, iv, &mut output)?;
+    Ok(output)
 }
 
-/// Implements decryption AES-128-CBC by using ECB mode
-pub fn aes_cbc_decrypt(bytes: &[u8], key: &[u8], iv: &[u8]) -> Result<Vec<u8>, PaddingError> {
-    let cipher = Cipher::aes_128_ecb();
-    let block_size = cipher.block_size();
-    let mut output: Vec<u8> = Vec::with_capacity(bytes.len());
-    let mut iv = iv.to_vec();
-    
-    for i in (0..bytes.len()).step_by(block_size) {
-        let mut crypter = Crypter::new(cipher, Mode::Decrypt, key, Some(&iv)).unwrap();
-        let slice = if i+block_size+1 < bytes.len() { 
-            &bytes[i..(i+block_size)] 
-        } else {
-            &bytes[i..] 
-        };
+/// AES-CBC decryption with a 128, 192 or 256 bit key, chosen by the
+/// length of `key`, then removes the padding
+pub fn aes_cbc_decrypt<P: Padding + ?Sized>(bytes: &[u8], key: &[u8], iv: &[u8], padding: &P) -> Result<Vec<u8>, Error> {
+    check_aes_cbc_params(key, iv)?;
+    if !bytes.len().is_multiple_of(16) {
+        return Err(Error::InvalidLength);
+    }
 
-        let mut buffer = [0; 32];
-        crypter.update(slice, &mut buffer).unwrap();
-        let xored = xor_string(&buffer, &iv);
-        iv = slice.to_vec();
-    
-        output.extend_from_slice(&xored[..16]);
+    let mut output = bytes.to_vec();
+    aes_cbc_in_place(true, key, iv, &mut output)?;
+    output.truncate(padding.unpadded_len(&output, 16)?);
+    Ok(output)
+}
+
+/// Compares two byte sequences in a time that depends only on their
+/// length, so that it doesn't reveal where they differ
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
     }
 
-    pkcs7_unpad(&output, block_size as u8)
+    let diff = a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y));
+    std::hint::black_box(diff) == 0
 }
 
 /// Generates a random key of the desired size
@@ -136,15 +384,30 @@ pub fn random_bytes(size: usize) -> Vec<u8> {
 
 #[cfg(test)]
 mod tests {
+    use crate::aes::Aes;
+    use crate::cipher::tests::Toy;
+    use crate::padding::NoPadding;
+    use crate::PaddingError;
     use crate::tools::{ToBytes, AsString};
+    #[cfg(feature = "openssl")]
+    use crate::cipher::OpensslAes;
+    #[cfg(feature = "openssl")]
+    use openssl::symm::encrypt;
 
     use super::*;
 
     #[test]
+    #[allow(clippy::char_lit_as_u8)]
     fn xor_char_works() {
         assert_eq!(xor_char(b"ABC444", 'v' as u8), "745BBB");
     }
 
+    #[test]
+    fn xor_byte_works() {
+        assert_eq!(xor_byte(b"ABC444", b'v'), b"745BBB");
+        assert_eq!(xor_byte(&[0x00, 0xFF], 0xFF), [0xFF, 0x00]);
+    }
+
     #[test]
     fn xor_works() {
         assert_eq!(xor_string(b"ABC444", b"v"), b"745BBB");
@@ -153,12 +416,12 @@ mod tests {
 
     #[test]
     fn pkcs7_pad_works() {
-        assert_eq!(pkcs7_pad(b"YELLOW SUBMARINE", 20), b"YELLOW SUBMARINE\x04\x04\x04\x04");
-        assert_eq!(pkcs7_pad(b"YELLOW SUBMARINE!", 20), b"YELLOW SUBMARINE!\x03\x03\x03");
-        assert_eq!(pkcs7_pad(b"YELLOW SUBMARINE!!", 20), b"YELLOW SUBMARINE!!\x02\x02");
-        assert_eq!(pkcs7_pad(b"YELLOW SUBMARINE!!!", 20), b"YELLOW SUBMARINE!!!\x01");
-        assert_eq!(pkcs7_pad(b"YELLOW SUBMARINE!!!!", 20), b"YELLOW SUBMARINE!!!!\x14\x14\x14\x14\x14\x14\x14\x14\x14\x14\x14\x14\x14\x14\x14\x14\x14\x14\x14\x14");
-        assert_eq!(pkcs7_pad(b"YELLOW SUBMARINE!!!!!", 20), b"YELLOW SUBMARINE!!!!!\x13\x13\x13\x13\x13\x13\x13\x13\x13\x13\x13\x13\x13\x13\x13\x13\x13\x13\x13");
+        assert_eq!(pkcs7_pad(b"YELLOW SUBMARINE", 20).unwrap(), b"YELLOW SUBMARINE\x04\x04\x04\x04");
+        assert_eq!(pkcs7_pad(b"YELLOW SUBMARINE!", 20).unwrap(), b"YELLOW SUBMARINE!\x03\x03\x03");
+        assert_eq!(pkcs7_pad(b"YELLOW SUBMARINE!!", 20).unwrap(), b"YELLOW SUBMARINE!!\x02\x02");
+        assert_eq!(pkcs7_pad(b"YELLOW SUBMARINE!!!", 20).unwrap(), b"YELLOW SUBMARINE!!!\x01");
+        assert_eq!(pkcs7_pad(b"YELLOW SUBMARINE!!!!", 20).unwrap(), b"YELLOW SUBMARINE!!!!\x14\x14\x14\x14\x14\x14\x14\x14\x14\x14\x14\x14\x14\x14\x14\x14\x14\x14\x14\x14");
+        assert_eq!(pkcs7_pad(b"YELLOW SUBMARINE!!!!!", 20).unwrap(), b"YELLOW SUBMARINE!!!!!\x13\x13\x13\x13\x13\x13\x13\x13\x13\x13\x13\x13\x13\x13\x13\x13\x13\x13\x13");
     }
 
     #[test]
@@ -168,11 +431,22 @@ mod tests {
         assert_eq!(pkcs7_unpad(b"YELLOW SUBMARINE!!\x12\x12\x12\x12\x12\x12\x12\x12\x12\x12\x12\x12\x12\x12\x12\x12\x12\x12", 18).unwrap(), b"YELLOW SUBMARINE!!");
         assert_eq!(pkcs7_unpad(b"YELLOW SUBMARINE!!!\x11\x11\x11\x11\x11\x11\x11\x11\x11\x11\x11\x11\x11\x11\x11\x11\x11", 18).unwrap(), b"YELLOW SUBMARINE!!!");
 
-        assert_eq!(pkcs7_unpad(b"YELLOW SUBMARINE\x01", 18), Err(PaddingError::InvalidLength));
-        assert_eq!(pkcs7_unpad(b"YELLOW SUBMARINE\x03\x03", 18), Err(PaddingError::InvalidPadding));    
+        assert_eq!(pkcs7_unpad(b"YELLOW SUBMARINE\x01", 18), Err(Error::InvalidLength));
+        assert_eq!(pkcs7_unpad(b"YELLOW SUBMARINE\x03\x03", 18), Err(Error::InvalidPadding(PaddingError::UnequalBytes)));    
+        assert_eq!(pkcs7_unpad(b"YELLOW SUBMARINE\x00\x00", 18), Err(Error::InvalidPadding(PaddingError::InvalidLengthByte(0))));
+        assert_eq!(pkcs7_unpad(b"YELLOW SUBMARINE", 0), Err(Error::InvalidBlockSize(0)));
+        assert_eq!(pkcs7_pad(b"YELLOW SUBMARINE", 0), Err(Error::InvalidBlockSize(0)));
     }
 
 
+    #[test]
+    fn constant_time_eq_works() {
+        assert!(constant_time_eq(b"tag", b"tag"));
+        assert!(constant_time_eq(b"", b""));
+        assert!(!constant_time_eq(b"tag", b"taG"));
+        assert!(!constant_time_eq(b"tag", b"tags"));
+    }
+
     #[test]
     fn aes_cbc_works() {
         let p1 = b"< 1 block";
@@ -184,16 +458,346 @@ mod tests {
         let key = b"YELLOW SUBMARINE";
         let iv = [0; 16];
 
-        assert_eq!(aes_cbc_decrypt(&aes_cbc_encrypt(p1, key, &iv), key, &iv).unwrap(), p1);
-        assert_eq!(aes_cbc_decrypt(&aes_cbc_encrypt(p2, key, &iv), key, &iv).unwrap(), p2);
-        assert_eq!(aes_cbc_decrypt(&aes_cbc_encrypt(p3, key, &iv), key, &iv).unwrap(), p3);
-        assert_eq!(aes_cbc_decrypt(&aes_cbc_encrypt(p4, key, &iv), key, &iv).unwrap(), p4);
-        assert_eq!(aes_cbc_decrypt(&aes_cbc_encrypt(p5, key, &iv), key, &iv).unwrap(), p5);
+        assert_eq!(aes_cbc_decrypt(&aes_cbc_encrypt(p1, key, &iv, &Pkcs7).unwrap(), key, &iv, &Pkcs7).unwrap(), p1);
+        assert_eq!(aes_cbc_decrypt(&aes_cbc_encrypt(p2, key, &iv, &Pkcs7).unwrap(), key, &iv, &Pkcs7).unwrap(), p2);
+        assert_eq!(aes_cbc_decrypt(&aes_cbc_encrypt(p3, key, &iv, &Pkcs7).unwrap(), key, &iv, &Pkcs7).unwrap(), p3);
+        assert_eq!(aes_cbc_decrypt(&aes_cbc_encrypt(p4, key, &iv, &Pkcs7).unwrap(), key, &iv, &Pkcs7).unwrap(), p4);
+        assert_eq!(aes_cbc_decrypt(&aes_cbc_encrypt(p5, key, &iv, &Pkcs7).unwrap(), key, &iv, &Pkcs7).unwrap(), p5);
+
+        assert_eq!(&aes_cbc_encrypt(p1, key, &iv, &Pkcs7).unwrap().as_base64(), "vXopWVGO/WC3SVZ7u68hGg==");
+        assert_eq!(&aes_cbc_encrypt(p5, key, &iv, &Pkcs7).unwrap().as_base64(), "xEspv6Mj7bwAOoH4TPUSTLiXj4FZLnLRBuEXu9mxKzu3S3ZVbrU6EzO7M4japWRT");
+
+        assert_eq!(aes_cbc_decrypt(&"vXopWVGO/WC3SVZ7u68hGg==".parse_base64().unwrap(), key, &iv, &Pkcs7).unwrap(), p1);
+        assert_eq!(aes_cbc_decrypt(&"xEspv6Mj7bwAOoH4TPUSTLiXj4FZLnLRBuEXu9mxKzu3S3ZVbrU6EzO7M4japWRT".parse_base64().unwrap(), key, &iv, &Pkcs7).unwrap(), p5);
+    }
+
+    #[test]
+    #[cfg(feature = "openssl")]
+    fn aes_cbc_handles_long_messages() {
+        // Longer than the chunks processed at once, and not aligned to them
+        let plaintext = random_bytes(3 * CBC_CHUNK + 100);
+        let key = random_bytes(24);
+        let iv = random_bytes(16);
+
+        let ciphertext = aes_cbc_encrypt(&plaintext, &key, &iv, &Pkcs7).unwrap();
+        assert_eq!(ciphertext, cbc_encrypt(&Aes::new(&key).unwrap(), &plaintext, &iv, &Pkcs7).unwrap());
+        assert_eq!(aes_cbc_decrypt(&ciphertext, &key, &iv, &Pkcs7).unwrap(), plaintext);
+    }
+
+    #[test]
+    fn aes_cbc_rejects_bad_input() {
+        let key = b"YELLOW SUBMARINE";
+        let iv = [0; 16];
+
+        assert_eq!(aes_cbc_encrypt(b"text", b"short key", &iv, &Pkcs7), Err(Error::InvalidKeyLength(9)));
+        assert_eq!(aes_cbc_encrypt(b"text", &[0; 20], &iv, &Pkcs7), Err(Error::InvalidKeyLength(20)));
+        assert_eq!(aes_cbc_decrypt(&[0; 16], &[0; 64], &iv, &Pkcs7), Err(Error::InvalidKeyLength(64)));
+        assert_eq!(aes_cbc_decrypt(&[0; 16], &[0; 32], &iv[..8], &Pkcs7), Err(Error::InvalidIvLength(8)));
+        assert_eq!(aes_cbc_encrypt(b"text", key, &iv[..4], &Pkcs7), Err(Error::InvalidIvLength(4)));
+        assert_eq!(aes_cbc_decrypt(&[0; 17], key, &iv, &Pkcs7), Err(Error::InvalidLength));
+        assert_eq!(aes_cbc_decrypt(&[], key, &iv, &Pkcs7), Err(Error::InvalidLength));
+    }
+
+    #[test]
+    fn modes_are_generic() {
+        let toy = Toy(*b"toy key!");
+        let plaintext = b"Same 8B!Same 8B!and some more";
+
+        let ecb = ecb_encrypt(&toy, plaintext, &Pkcs7).unwrap();
+        assert_eq!(ecb.len(), 32);
+        assert_eq!(ecb[..8], ecb[8..16]);
+        assert_eq!(ecb_decrypt(&toy, &ecb, &Pkcs7).unwrap(), plaintext);
+
+        let cbc = cbc_encrypt(&toy, plaintext, b"8B IV!!!", &Pkcs7).unwrap();
+        assert_ne!(cbc[..8], cbc[8..16]);
+        assert_eq!(cbc_decrypt(&toy, &cbc, b"8B IV!!!", &Pkcs7).unwrap(), plaintext);
+
+        assert_eq!(cbc_encrypt(&toy, plaintext, &[0; 16], &Pkcs7), Err(Error::InvalidIvLength(16)));
+        assert_eq!(ecb_decrypt(&toy, &ecb[..30], &Pkcs7), Err(Error::InvalidLength));
+        assert_eq!(cbc_decrypt(&toy, &[], b"8B IV!!!", &Pkcs7), Err(Error::InvalidLength));
+    }
+
+    #[test]
+    fn modes_take_any_padding() {
+        use crate::padding::{AnsiX923, Iso10126, Iso7816, ZeroPadding};
+
+        let key = b"YELLOW SUBMARINE";
+        let iv = [0; 16];
+        let plaintext = b"Just a little something more than two blocks";
+        let paddings: [&dyn Padding; 5] = [&Pkcs7, &AnsiX923, &Iso10126, &Iso7816, &ZeroPadding];
+
+        for padding in paddings {
+            let ecb = aes_ecb_encrypt(plaintext, key, padding).unwrap();
+            assert_eq!(ecb.len(), 48);
+            assert_eq!(aes_ecb_decrypt(&ecb, key, padding).unwrap(), plaintext);
+
+            let cbc = aes_cbc_encrypt(plaintext, key, &iv, padding).unwrap();
+            assert_eq!(aes_cbc_decrypt(&cbc, key, &iv, padding).unwrap(), plaintext);
+
+            let aes = Aes::new(key).unwrap();
+            let pcbc = pcbc_encrypt(&aes, plaintext, &iv, padding).unwrap();
+            assert_eq!(pcbc_decrypt(&aes, &pcbc, &iv, padding).unwrap(), plaintext);
+        }
+
+        // A full block of zeros is not a valid ANSI X.923 or ISO 7816-4
+        // padding, and each scheme reports why
+        let zeros = aes_ecb_encrypt(&[0; 16], key, &NoPadding).unwrap();
+        assert_eq!(aes_ecb_decrypt(&zeros, key, &AnsiX923), Err(Error::InvalidPadding(PaddingError::InvalidLengthByte(0))));
+        assert_eq!(aes_ecb_decrypt(&zeros, key, &Iso7816), Err(Error::InvalidPadding(PaddingError::MissingMarker)));
+        // Zero padding never adds a whole block, so at most 15 are removed
+        assert_eq!(aes_ecb_decrypt(&zeros, key, &ZeroPadding).unwrap(), [0]);
+    }
+
+    #[test]
+    #[cfg(feature = "openssl")]
+    fn modes_match_openssl() {
+        let key = b"YELLOW SUBMARINE";
+        let iv = b"0123456789abcdef";
+        let plaintext = b"Just a little something more than two blocks";
+
+        for cipher in [&Aes::new(key).unwrap() as &dyn BlockCipher, &OpensslAes::new(key).unwrap()] {
+            let ecb = ecb_encrypt(cipher, plaintext, &Pkcs7).unwrap();
+            assert_eq!(ecb, encrypt(Cipher::aes_128_ecb(), key, None, plaintext).unwrap());
+            assert_eq!(ecb_decrypt(cipher, &ecb, &Pkcs7).unwrap(), plaintext);
+
+            let cbc = cbc_encrypt(cipher, plaintext, iv, &Pkcs7).unwrap();
+            assert_eq!(cbc, encrypt(Cipher::aes_128_cbc(), key, Some(iv), plaintext).unwrap());
+            assert_eq!(cbc_decrypt(cipher, &cbc, iv, &Pkcs7).unwrap(), plaintext);
+        }
+    }
+
+    #[test]
+    fn aes_ecb_works() {
+        let key = b"YELLOW SUBMARINE";
+        let two_blocks = b"Exactly 2 blocksExactly 2 blocks";
+
+        let padded = aes_ecb_encrypt(two_blocks, key, &Pkcs7).unwrap();
+        assert_eq!(padded.len(), 48);
+        assert_eq!(aes_ecb_decrypt(&padded, key, &Pkcs7).unwrap(), two_blocks);
+
+        let unpadded = aes_ecb_encrypt(two_blocks, key, &NoPadding).unwrap();
+        assert_eq!(unpadded, padded[..32]);
+        assert_eq!(aes_ecb_decrypt(&unpadded, key, &NoPadding).unwrap(), two_blocks);
+        // The last block decrypts to text, which is not valid padding
+        assert_eq!(aes_ecb_decrypt(&unpadded, key, &Pkcs7), Err(Error::InvalidPadding(PaddingError::InvalidLengthByte(b's'))));
+
+        assert_eq!(aes_ecb_encrypt(b"", key, &NoPadding).unwrap(), b"");
+        assert_eq!(aes_ecb_decrypt(b"", key, &NoPadding).unwrap(), b"");
+        assert_eq!(aes_ecb_encrypt(b"short", key, &NoPadding), Err(Error::InvalidLength));
+        assert_eq!(aes_ecb_decrypt(&padded[..40], key, &Pkcs7), Err(Error::InvalidLength));
+        assert_eq!(aes_ecb_decrypt(b"", key, &Pkcs7), Err(Error::InvalidLength));
+        assert_eq!(aes_ecb_encrypt(b"text", b"short key", &Pkcs7), Err(Error::InvalidKeyLength(9)));
+    }
+
+    // NIST SP 800-38A, appendix F, with AES-128
+    const NIST_KEY: &str = "2b7e151628aed2a6abf7158809cf4f3c";
+    const NIST_IV: &str = "000102030405060708090a0b0c0d0e0f";
+    const NIST_PLAINTEXT: &str = "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51\
+                                  30c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710";
+
+    #[test]
+    fn aes_cbc_matches_nist() {
+        // F.2.1, F.2.3 and F.2.5: CBC-AES128, CBC-AES192, CBC-AES256
+        let vectors = [
+            (NIST_KEY,
+             "7649abac8119b246cee98e9b12e9197d5086cb9b507219ee95db113a917678b2\
+              73bed6b8e3c1743b7116e69e222295163ff1caa1681fac09120eca307586e1a7"),
+            ("8e73b0f7da0e6452c810f32b809079e562f8ead2522c6b7b",
+             "4f021db243bc633d7178183a9fa071e8b4d9ada9ad7dedf4e5e738763f69145a\
+              571b242012fb7ae07fa9baac3df102e008b0e27988598881d920a9e64f5615cd"),
+            ("603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4",
+             "f58c4c04d6e5f1ba779eabfb5f7bfbd69cfc4e967edb808d679f777bc6702c7d\
+              39f23369a9d9bacfa530e26304231461b2eb05e2c39be9fcda6c19078c6a9d1b"),
+        ];
+        let iv = NIST_IV.parse_hex().unwrap();
+        let plaintext = NIST_PLAINTEXT.parse_hex().unwrap();
+
+        for (key, expected) in vectors {
+            let key = key.parse_hex().unwrap();
+            let expected = expected.parse_hex().unwrap();
+
+            // The vectors have no padding, which adds a whole block here
+            let ciphertext = aes_cbc_encrypt(&plaintext, &key, &iv, &Pkcs7).unwrap();
+            assert_eq!(ciphertext.len(), 80);
+            assert_eq!(ciphertext[..64], expected);
+            assert_eq!(aes_cbc_decrypt(&ciphertext, &key, &iv, &Pkcs7).unwrap(), plaintext);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "openssl")]
+    fn aes_cbc_matches_openssl() {
+        let iv = random_bytes(16);
+        let key = random_bytes(32);
+        let expected = encrypt(Cipher::aes_256_cbc(), &key, Some(&iv), b"some text").unwrap();
+        assert_eq!(aes_cbc_encrypt(b"some text", &key, &iv, &Pkcs7).unwrap(), expected);
+        let key = random_bytes(24);
+        let expected = encrypt(Cipher::aes_192_cbc(), &key, Some(&iv), b"some text").unwrap();
+        assert_eq!(aes_cbc_decrypt(&expected, &key, &iv, &Pkcs7).unwrap(), b"some text");
+    }
+
+    #[test]
+    fn stream_modes_match_nist() {
+        let aes = Aes::new(&NIST_KEY.parse_hex().unwrap()).unwrap();
+        let iv = NIST_IV.parse_hex().unwrap();
+        let plaintext = NIST_PLAINTEXT.parse_hex().unwrap();
+
+        // F.3.7, CFB8-AES128
+        let cfb8 = "3b79424c9c0dd436bace9e0ed4586a4f32b9".parse_hex().unwrap();
+        assert_eq!(cfb_encrypt(&aes, &plaintext[..18], &iv, CfbSegment::Byte).unwrap(), cfb8);
+        assert_eq!(cfb_decrypt(&aes, &cfb8, &iv, CfbSegment::Byte).unwrap(), plaintext[..18]);
+
+        // F.3.13, CFB128-AES128
+        let cfb128 = "3b3fd92eb72dad20333449f8e83cfb4ac8a64537a0b3a93fcde3cdad9f1ce58b\
+                      26751f67a3cbb140b1808cf187a4f4dfc04b05357c5d1c0eeac4c66f9ff7f2e6".parse_hex().unwrap();
+        assert_eq!(cfb_encrypt(&aes, &plaintext, &iv, CfbSegment::Block).unwrap(), cfb128);
+        assert_eq!(cfb_decrypt(&aes, &cfb128, &iv, CfbSegment::Block).unwrap(), plaintext);
+
+        // F.4.1, OFB-AES128
+        let ofb = "3b3fd92eb72dad20333449f8e83cfb4a7789508d16918f03f53c52dac54ed825\
+                   9740051e9c5fecf64344f7a82260edcc304c6528f659c77866a510d9c1d6ae5e".parse_hex().unwrap();
+        assert_eq!(ofb_encrypt(&aes, &plaintext, &iv).unwrap(), ofb);
+        assert_eq!(ofb_decrypt(&aes, &ofb, &iv).unwrap(), plaintext);
+
+        // Partial final segments
+        assert_eq!(cfb_encrypt(&aes, &plaintext[..20], &iv, CfbSegment::Block).unwrap(), cfb128[..20]);
+        assert_eq!(cfb_decrypt(&aes, &cfb128[..20], &iv, CfbSegment::Block).unwrap(), plaintext[..20]);
+        assert_eq!(ofb_encrypt(&aes, &plaintext[..20], &iv).unwrap(), ofb[..20]);
+    }
+
+    #[test]
+    #[cfg(feature = "openssl")]
+    fn stream_modes_match_openssl() {
+        let key = random_bytes(16);
+        let iv = random_bytes(16);
+        let plaintext = random_bytes(100);
+        let aes = Aes::new(&key).unwrap();
+
+        let expected = encrypt(Cipher::aes_128_cfb8(), &key, Some(&iv), &plaintext).unwrap();
+        assert_eq!(cfb_encrypt(&aes, &plaintext, &iv, CfbSegment::Byte).unwrap(), expected);
+        let expected = encrypt(Cipher::aes_128_cfb128(), &key, Some(&iv), &plaintext).unwrap();
+        assert_eq!(cfb_encrypt(&aes, &plaintext, &iv, CfbSegment::Block).unwrap(), expected);
+        let expected = encrypt(Cipher::aes_128_ofb(), &key, Some(&iv), &plaintext).unwrap();
+        assert_eq!(ofb_encrypt(&aes, &plaintext, &iv).unwrap(), expected);
+    }
+
+    #[test]
+    fn pcbc_works() {
+        let toy = Toy(*b"toy key!");
+        let iv = b"8B IV!!!";
+        let plaintext = b"Same 8B!Same 8B!and some more";
+
+        let pcbc = pcbc_encrypt(&toy, plaintext, iv, &Pkcs7).unwrap();
+        assert_eq!(pcbc.len(), 32);
+        assert_eq!(pcbc_decrypt(&toy, &pcbc, iv, &Pkcs7).unwrap(), plaintext);
+        // The first block is the same as in CBC
+        assert_eq!(pcbc[..8], cbc_encrypt(&toy, plaintext, iv, &Pkcs7).unwrap()[..8]);
+
+        let unpadded = pcbc_encrypt(&toy, &plaintext[..24], iv, &NoPadding).unwrap();
+        assert_eq!(unpadded, pcbc[..24]);
+        assert_eq!(pcbc_decrypt(&toy, &unpadded, iv, &NoPadding).unwrap(), plaintext[..24]);
+
+        // Swapping two blocks only corrupts those two
+        let mut swapped = unpadded.clone();
+        let (a, b) = swapped.split_at_mut(8);
+        a.swap_with_slice(&mut b[..8]);
+        let decrypted = pcbc_decrypt(&toy, &swapped, iv, &NoPadding).unwrap();
+        assert_ne!(decrypted[..16], plaintext[..16]);
+        assert_eq!(decrypted[16..], plaintext[16..24]);
+
+        assert_eq!(pcbc_encrypt(&toy, plaintext, iv, &NoPadding), Err(Error::InvalidLength));
+        assert_eq!(pcbc_encrypt(&toy, plaintext, &[0; 16], &Pkcs7), Err(Error::InvalidIvLength(16)));
+        assert_eq!(pcbc_decrypt(&toy, &pcbc[..30], iv, &Pkcs7), Err(Error::InvalidLength));
+        assert_eq!(cfb_encrypt(&toy, plaintext, &[0; 16], CfbSegment::Byte), Err(Error::InvalidIvLength(16)));
+        assert_eq!(ofb_decrypt(&toy, plaintext, &[0; 7]), Err(Error::InvalidIvLength(7)));
+    }
 
-        assert_eq!(&aes_cbc_encrypt(p1, key, &iv).as_base64(), "vXopWVGO/WC3SVZ7u68hGg==");
-        assert_eq!(&aes_cbc_encrypt(p5, key, &iv).as_base64(), "xEspv6Mj7bwAOoH4TPUSTLiXj4FZLnLRBuEXu9mxKzu3S3ZVbrU6EzO7M4japWRT");
+    #[test]
+    fn zero_block_size_is_rejected() {
+        struct Empty;
+
+        impl BlockCipher for Empty {
+            fn block_size(&self) -> usize {
+                0
+            }
+
+            fn encrypt_block(&self, _: &mut [u8]) -> Result<(), Error> {
+                Ok(())
+            }
+
+            fn decrypt_block(&self, _: &mut [u8]) -> Result<(), Error> {
+                Ok(())
+            }
+        }
+
+        assert_eq!(ecb_encrypt(&Empty, b"abc", &NoPadding), Err(Error::InvalidBlockSize(0)));
+        assert_eq!(cbc_encrypt(&Empty, b"abc", &[], &NoPadding), Err(Error::InvalidBlockSize(0)));
+        assert_eq!(cfb_encrypt(&Empty, b"abc", &[], CfbSegment::Byte), Err(Error::InvalidBlockSize(0)));
+        assert_eq!(cfb_decrypt(&Empty, b"abc", &[], CfbSegment::Block), Err(Error::InvalidBlockSize(0)));
+        assert_eq!(ofb_encrypt(&Empty, b"abc", &[]), Err(Error::InvalidBlockSize(0)));
+        assert_eq!(ofb_decrypt(&Empty, b"abc", &[]), Err(Error::InvalidBlockSize(0)));
+    }
 
-        assert_eq!(aes_cbc_decrypt(&"vXopWVGO/WC3SVZ7u68hGg==".parse_base64().unwrap(), key, &iv).unwrap(), p1);
-        assert_eq!(aes_cbc_decrypt(&"xEspv6Mj7bwAOoH4TPUSTLiXj4FZLnLRBuEXu9mxKzu3S3ZVbrU6EzO7M4japWRT".parse_base64().unwrap(), key, &iv).unwrap(), p5);
+    /// Flips the lowest bit of byte 20 (in the second block) of the
+    /// ciphertext, decrypts it, and describes each byte of the result:
+    /// '.' if intact, '^' if only that same bit is flipped, '#' if
+    /// otherwise garbled. Blocks are separated by spaces.
+    fn corruption<D: Fn(&[u8]) -> Vec<u8>>(ciphertext: &[u8], plaintext: &[u8], decrypt: D) -> String {
+        let mut ciphertext = ciphertext.to_vec();
+        ciphertext[20] ^= 1;
+        let decrypted = decrypt(&ciphertext);
+
+        let pattern: String = decrypted.iter().zip(plaintext)
+            .map(|(a, b)| match a ^ b {
+                0 => '.',
+                1 => '^',
+                _ => '#',
+            })
+            .collect();
+
+        pattern.as_bytes().chunks(16).map(|c| String::from_utf8_lossy(c)).collect::<Vec<_>>().join(" ")
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn errors_propagate() {
+        let aes = Aes::new(&NIST_KEY.parse_hex().unwrap()).unwrap();
+        let iv = NIST_IV.parse_hex().unwrap();
+        let plaintext = NIST_PLAINTEXT.parse_hex().unwrap();
+
+        // CBC: the block is garbled, and the bit flipped in the next one
+        let ciphertext = cbc_encrypt(&aes, &plaintext, &iv, &Pkcs7).unwrap();
+        assert_eq!(
+            corruption(&ciphertext, &plaintext, |c| cbc_decrypt(&aes, c, &iv, &Pkcs7).unwrap()),
+            "................ ################ ....^........... ................",
+        );
+
+        // PCBC: the error spreads to the end of the message
+        let ciphertext = pcbc_encrypt(&aes, &plaintext, &iv, &NoPadding).unwrap();
+        assert_eq!(
+            corruption(&ciphertext, &plaintext, |c| pcbc_decrypt(&aes, c, &iv, &NoPadding).unwrap()),
+            "................ ################ ################ ################",
+        );
+
+        // CFB-128: the bit is flipped, and the next block garbled
+        let ciphertext = cfb_encrypt(&aes, &plaintext, &iv, CfbSegment::Block).unwrap();
+        assert_eq!(
+            corruption(&ciphertext, &plaintext, |c| cfb_decrypt(&aes, c, &iv, CfbSegment::Block).unwrap()),
+            "................ ....^........... ################ ................",
+        );
+
+        // CFB-8: the bit is flipped, and the next 16 bytes garbled
+        // while the wrong byte is in the shift register
+        let ciphertext = cfb_encrypt(&aes, &plaintext, &iv, CfbSegment::Byte).unwrap();
+        assert_eq!(
+            corruption(&ciphertext, &plaintext, |c| cfb_decrypt(&aes, c, &iv, CfbSegment::Byte).unwrap()),
+            "................ ....^########### #####........... ................",
+        );
+
+        // OFB: only the bit is flipped
+        let ciphertext = ofb_encrypt(&aes, &plaintext, &iv).unwrap();
+        assert_eq!(
+            corruption(&ciphertext, &plaintext, |c| ofb_decrypt(&aes, c, &iv).unwrap()),
+            "................ ....^........... ................ ................",
+        );
+    }
+}
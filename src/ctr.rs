@@ -0,0 +1,296 @@
+use crate::cipher::{BlockCipher, DefaultAes};
+use crate::Error;
+
+/// Which part of the counter block is incremented, and how. The rest
+/// of the block is a fixed nonce. Counters wrap around silently, so
+/// the keystream repeats after 2^width blocks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CounterLayout {
+    /// The last 64 bits are a little-endian counter, after a 64-bit
+    /// nonce (the format of the Cryptopals challenges)
+    LittleEndian64,
+    /// The last 32 bits are a big-endian counter, after a 96-bit
+    /// nonce (as in NIST SP 800-38D)
+    BigEndian32,
+    /// The whole block is a big-endian counter (as in openssl)
+    BigEndianFull,
+}
+
+impl CounterLayout {
+    /// Size in bytes of the counter, for the given block size
+    fn width(&self, block_size: usize) -> usize {
+        match self {
+            CounterLayout::LittleEndian64 => 8,
+            CounterLayout::BigEndian32 => 4,
+            CounterLayout::BigEndianFull => block_size,
+        }
+    }
+
+    /// Adds `n` to the counter in `block`, modulo its width
+    fn add(&self, block: &mut [u8], n: u64) {
+        let start = block.len() - self.width(block.len());
+        let mut carry = n as u128;
+
+        let mut add_byte = |b: &mut u8| {
+            carry += *b as u128;
+            *b = carry as u8;
+            carry >>= 8;
+        };
+
+        match self {
+            CounterLayout::LittleEndian64 => block[start..].iter_mut().for_each(&mut add_byte),
+            _ => block[start..].iter_mut().rev().for_each(&mut add_byte),
+        }
+    }
+}
+
+/// Counter mode: a block cipher encrypts successive counter blocks
+/// to produce a keystream, which is XORed with the data. Encryption
+/// and decryption are the same operation, and any part of the data
+/// can be processed on its own.
+#[derive(Debug, Clone)]
+pub struct Ctr<C: BlockCipher> {
+    cipher: C,
+    initial: Vec<u8>,
+    layout: CounterLayout,
+}
+
+impl<C: BlockCipher> Ctr<C> {
+    /// `initial` is the first counter block (nonce and counter), as
+    /// long as a block of the cipher
+    pub fn new(cipher: C, initial: &[u8], layout: CounterLayout) -> Result<Self, Error> {
+        let block_size = cipher.block_size();
+        if block_size == 0 || layout.width(block_size) > block_size {
+            return Err(Error::InvalidBlockSize(block_size));
+        }
+        if initial.len() != block_size {
+            return Err(Error::InvalidIvLength(initial.len()));
+        }
+
+        Ok(Ctr { cipher, initial: initial.to_vec(), layout })
+    }
+
+    pub fn layout(&self) -> CounterLayout {
+        self.layout
+    }
+
+    /// The counter block for the given block of the keystream
+    pub fn counter_block(&self, index: u64) -> Vec<u8> {
+        let mut block = self.initial.clone();
+        self.layout.add(&mut block, index);
+        block
+    }
+
+    /// The keystream, starting at byte `offset`
+    pub fn keystream_at(&self, offset: u64) -> Keystream<'_, C> {
+        Keystream { ctr: self, position: offset, block: Vec::new(), index: None }
+    }
+
+    /// Encrypts or decrypts (it's the same operation) in place data
+    /// found at `offset` in the stream
+    pub fn apply_at(&self, offset: u64, bytes: &mut [u8]) -> Result<(), Error> {
+        self.keystream_at(offset).apply(bytes)
+    }
+
+    /// Encrypts or decrypts a whole stream
+    pub fn crypt(&self, bytes: &[u8]) -> Result<Vec<u8>, Error> {
+        let mut output = bytes.to_vec();
+        self.apply_at(0, &mut output)?;
+        Ok(output)
+    }
+
+    /// Replaces the plaintext at `offset` in a ciphertext with
+    /// `newtext`, without touching the rest. Fails if `newtext`
+    /// doesn't fit.
+    pub fn edit(&self, ciphertext: &mut [u8], offset: usize, newtext: &[u8]) -> Result<(), Error> {
+        let end = offset.checked_add(newtext.len()).filter(|&end| end <= ciphertext.len()).ok_or(Error::InvalidLength)?;
+        let target = &mut ciphertext[offset..end];
+        target.copy_from_slice(newtext);
+        self.apply_at(offset as u64, target)
+    }
+}
+
+/// A CTR keystream, which can be read from any position
+pub struct Keystream<'a, C: BlockCipher> {
+    ctr: &'a Ctr<C>,
+    position: u64,
+    /// Cached keystream block, and its index
+    block: Vec<u8>,
+    index: Option<u64>,
+}
+
+impl<C: BlockCipher> Keystream<'_, C> {
+    /// Position in bytes of the next keystream byte
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    pub fn seek(&mut self, offset: u64) {
+        self.position = offset;
+    }
+
+    /// XORs the next keystream bytes into `bytes`. Fails, leaving
+    /// them untouched, if they go past the end of the stream.
+    pub fn apply(&mut self, bytes: &mut [u8]) -> Result<(), Error> {
+        let block_size = self.ctr.cipher.block_size() as u64;
+        self.position.checked_add(bytes.len() as u64).ok_or(Error::InvalidLength)?;
+        let mut bytes = bytes;
+
+        while !bytes.is_empty() {
+            let index = self.position / block_size;
+            if self.index != Some(index) {
+                self.block = self.ctr.counter_block(index);
+                self.ctr.cipher.encrypt_block(&mut self.block)?;
+                self.index = Some(index);
+            }
+
+            let start = (self.position % block_size) as usize;
+            let n = bytes.len().min(self.block.len() - start);
+            let (chunk, rest) = bytes.split_at_mut(n);
+            chunk.iter_mut().zip(&self.block[start..]).for_each(|(b, k)| *b ^= k);
+
+            bytes = rest;
+            self.position += n as u64;
+        }
+
+        Ok(())
+    }
+
+    /// Fills `buffer` with the next keystream bytes
+    pub fn fill(&mut self, buffer: &mut [u8]) -> Result<(), Error> {
+        buffer.fill(0);
+        self.apply(buffer)
+    }
+}
+
+/// AES-CTR with a 128, 192 or 256 bit key and a 16-byte initial
+/// counter block, see `Ctr`
+pub fn aes_ctr(bytes: &[u8], key: &[u8], initial: &[u8], layout: CounterLayout) -> Result<Vec<u8>, Error> {
+    Ctr::new(DefaultAes::new(key)?, initial, layout)?.crypt(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aes::Aes;
+    use crate::cipher::tests::Toy;
+    use crate::crypto::random_bytes;
+    use crate::tools::ToBytes;
+    #[cfg(feature = "openssl")]
+    use openssl::symm::{encrypt, Cipher};
+
+    #[test]
+    fn cryptopals_ctr_works() {
+        let ciphertext = "L77na/nrFsKvynd6HzOoG7GHTLXsTVu9qvY/2syLXzhPweyyMTJULu/6/kXX0KSvoOLSFQ==".parse_base64().unwrap();
+        let plaintext = aes_ctr(&ciphertext, b"YELLOW SUBMARINE", &[0; 16], CounterLayout::LittleEndian64).unwrap();
+        assert_eq!(plaintext, b"Yo, VIP Let's kick it Ice, Ice, baby Ice, Ice, baby ");
+    }
+
+    #[test]
+    fn nist_ctr_works() {
+        // NIST SP 800-38A, F.5.1 and F.5.5
+        let plaintext = "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51\
+                         30c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710".parse_hex().unwrap();
+        let initial = "f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff".parse_hex().unwrap();
+        let vectors = [
+            ("2b7e151628aed2a6abf7158809cf4f3c",
+             "874d6191b620e3261bef6864990db6ce9806f66b7970fdff8617187bb9fffdff\
+              5ae4df3edbd5d35e5b4f09020db03eab1e031dda2fbe03d1792170a0f3009cee"),
+            ("603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4",
+             "601ec313775789a5b7a7f504bbf3d228f443e3ca4d62b59aca84e990cacaf5c5\
+              2b0930daa23de94ce87017ba2d84988ddfc9c58db67aada613c2dd08457941a6"),
+        ];
+
+        for (key, expected) in vectors {
+            let key = key.parse_hex().unwrap();
+            let expected = expected.parse_hex().unwrap();
+            for layout in [CounterLayout::BigEndian32, CounterLayout::BigEndianFull] {
+                let ctr = Ctr::new(Aes::new(&key).unwrap(), &initial, layout).unwrap();
+                assert_eq!(ctr.crypt(&plaintext).unwrap(), expected);
+                assert_eq!(ctr.crypt(&expected).unwrap(), plaintext);
+            }
+        }
+    }
+
+    #[test]
+    fn counters_wrap_within_their_width() {
+        let initial = [0xff; 16];
+        let ctr = |layout| Ctr::new(Toy([0; 8]), &initial[..8], layout).unwrap();
+
+        assert_eq!(ctr(CounterLayout::BigEndian32).counter_block(1), [0xff, 0xff, 0xff, 0xff, 0, 0, 0, 0]);
+        assert_eq!(ctr(CounterLayout::BigEndian32).counter_block(2), [0xff, 0xff, 0xff, 0xff, 0, 0, 0, 1]);
+        assert_eq!(ctr(CounterLayout::BigEndianFull).counter_block(2), [0, 0, 0, 0, 0, 0, 0, 1]);
+        assert_eq!(ctr(CounterLayout::LittleEndian64).counter_block(2), [1, 0, 0, 0, 0, 0, 0, 0]);
+
+        let nonce = Ctr::new(Toy([0; 8]), &[0; 8], CounterLayout::BigEndian32).unwrap();
+        assert_eq!(nonce.counter_block(0x1_0000_0102), [0, 0, 0, 0, 0, 0, 1, 2]);
+
+        let le = Ctr::new(Aes::new(&[0; 16]).unwrap(), &[0; 16], CounterLayout::LittleEndian64).unwrap();
+        assert_eq!(le.counter_block(0x0102), [0, 0, 0, 0, 0, 0, 0, 0, 2, 1, 0, 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    #[cfg(feature = "openssl")]
+    fn ctr_matches_openssl() {
+        let key = random_bytes(16);
+        // Close to the end of the counter range, to check the carries
+        let mut initial = random_bytes(16);
+        initial[8..15].fill(0xff);
+        let plaintext = random_bytes(1000);
+
+        let ours = aes_ctr(&plaintext, &key, &initial, CounterLayout::BigEndianFull).unwrap();
+        assert_eq!(ours, encrypt(Cipher::aes_128_ctr(), &key, Some(&initial), &plaintext).unwrap());
+    }
+
+    #[test]
+    fn keystream_is_seekable() {
+        let ctr = Ctr::new(Aes::new(&random_bytes(16)).unwrap(), &random_bytes(16), CounterLayout::LittleEndian64).unwrap();
+        let mut full = vec![0; 100];
+        ctr.keystream_at(0).fill(&mut full).unwrap();
+
+        let mut keystream = ctr.keystream_at(37);
+        let mut part = vec![0; 20];
+        keystream.fill(&mut part).unwrap();
+        assert_eq!(part, full[37..57]);
+        assert_eq!(keystream.position(), 57);
+
+        keystream.seek(5);
+        keystream.fill(&mut part[..3]).unwrap();
+        keystream.fill(&mut part[3..]).unwrap();
+        assert_eq!(part, full[5..25]);
+
+        let plaintext = random_bytes(100);
+        let ciphertext = ctr.crypt(&plaintext).unwrap();
+        let mut middle = ciphertext[30..70].to_vec();
+        ctr.apply_at(30, &mut middle).unwrap();
+        assert_eq!(middle, plaintext[30..70]);
+
+        keystream.seek(u64::MAX - 1);
+        keystream.fill(&mut part[..1]).unwrap();
+        assert_eq!(keystream.fill(&mut part[..2]), Err(Error::InvalidLength));
+        assert_eq!(keystream.position(), u64::MAX);
+        assert_eq!(ctr.apply_at(u64::MAX - 2, &mut middle), Err(Error::InvalidLength));
+        assert_eq!(middle, plaintext[30..70]);
+    }
+
+    #[test]
+    fn edit_works() {
+        let ctr = Ctr::new(Aes::new(&random_bytes(16)).unwrap(), &[0; 16], CounterLayout::LittleEndian64).unwrap();
+        let mut ciphertext = ctr.crypt(b"The quick brown fox jumps over the lazy dog").unwrap();
+
+        ctr.edit(&mut ciphertext, 16, b"cat").unwrap();
+        assert_eq!(ctr.crypt(&ciphertext).unwrap(), b"The quick brown cat jumps over the lazy dog");
+        ctr.edit(&mut ciphertext, 40, b"cow").unwrap();
+        assert_eq!(ctr.crypt(&ciphertext).unwrap(), b"The quick brown cat jumps over the lazy cow");
+
+        assert_eq!(ctr.edit(&mut ciphertext, 41, b"cow"), Err(Error::InvalidLength));
+        assert_eq!(ctr.edit(&mut ciphertext, usize::MAX, b"cow"), Err(Error::InvalidLength));
+    }
+
+    #[test]
+    fn bad_parameters_are_rejected() {
+        assert!(matches!(Ctr::new(Toy([0; 8]), &[0; 16], CounterLayout::BigEndian32), Err(Error::InvalidIvLength(16))));
+        assert!(matches!(aes_ctr(b"", &[0; 16], &[0; 12], CounterLayout::BigEndian32), Err(Error::InvalidIvLength(12))));
+        assert!(matches!(aes_ctr(b"", &[0; 10], &[0; 16], CounterLayout::BigEndian32), Err(Error::InvalidKeyLength(10))));
+    }
+}
@@ -0,0 +1,168 @@
+use crate::encoding::Encoding;
+use crate::Error;
+
+const Z85_ALPHABET: &[u8; 85] = b"0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ.-:+=^!/*?&<>()[]{}@%$#";
+
+/// Ascii85 encoding (btoa/Adobe variant, digits from '!' to 'u').
+///
+/// A group of four zero bytes is encoded as 'z'. The "<~" and "~>"
+/// delimiters are not written, but are accepted when decoding,
+/// together with whitespace.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ascii85;
+
+/// Z85 encoding (ZeroMQ RFC 32).
+///
+/// The spec requires a length multiple of 4; other lengths are
+/// handled as in Ascii85, by encoding a partial last group.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Z85;
+
+/// Encodes groups of 4 bytes as 5 base85 digits, a partial
+/// group of n bytes is zero-padded and written as n+1 digits.
+fn encode85(bytes: &[u8], digit: impl Fn(u32) -> u8, zero_group: Option<u8>) -> String {
+    let mut out = Vec::with_capacity(bytes.len().div_ceil(4) * 5);
+
+    for group in bytes.chunks(4) {
+        let mut buffer = [0u8; 4];
+        buffer[..group.len()].copy_from_slice(group);
+        let value = u32::from_be_bytes(buffer);
+
+        if let (Some(z), 4, 0) = (zero_group, group.len(), value) {
+            out.push(z);
+            continue;
+        }
+
+        let mut digits = [0u8; 5];
+        let mut v = value;
+        for d in digits.iter_mut().rev() {
+            *d = digit(v % 85);
+            v /= 85;
+        }
+        out.extend_from_slice(&digits[..group.len() + 1]);
+    }
+
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Inverse of `encode85`, `value` maps a char to its digit and
+/// returns None for chars to skip.
+fn decode85(
+    input: &[u8],
+    value: impl Fn(u8) -> Result<Option<u32>, Error>,
+    zero_group: Option<u8>,
+) -> Result<Vec<u8>, Error> {
+    let mut out = Vec::with_capacity(input.len() / 5 * 4 + 4);
+    let mut digits = Vec::with_capacity(5);
+
+    let push_group = |digits: &mut Vec<u32>, out: &mut Vec<u8>| -> Result<(), Error> {
+        let n = digits.len();
+        // Missing digits are the highest ones, so the result is rounded up
+        digits.resize(5, 84);
+
+        let v = digits.iter().fold(0u64, |acc, d| acc * 85 + *d as u64);
+        if v > u32::MAX as u64 {
+            return Err(Error::InvalidAscii85String);
+        }
+
+        out.extend_from_slice(&(v as u32).to_be_bytes()[..n - 1]);
+        digits.clear();
+        Ok(())
+    };
+
+    for &c in input {
+        if Some(c) == zero_group {
+            if !digits.is_empty() {
+                return Err(Error::InvalidAscii85String);
+            }
+            out.extend_from_slice(&[0; 4]);
+            continue;
+        }
+
+        if let Some(d) = value(c)? {
+            digits.push(d);
+            if digits.len() == 5 {
+                push_group(&mut digits, &mut out)?;
+            }
+        }
+    }
+
+    match digits.len() {
+        0 => Ok(out),
+        // A single digit can't encode a whole byte
+        1 => Err(Error::InvalidAscii85String),
+        _ => push_group(&mut digits, &mut out).map(|_| out),
+    }
+}
+
+impl Encoding for Ascii85 {
+    fn encode(&self, bytes: &[u8]) -> String {
+        encode85(bytes, |d| d as u8 + b'!', Some(b'z'))
+    }
+
+    fn decode(&self, s: &str) -> Result<Vec<u8>, Error> {
+        let s = s.trim();
+        let s = s.strip_prefix("<~").unwrap_or(s);
+        let s = s.strip_suffix("~>").unwrap_or(s);
+
+        decode85(s.as_bytes(), |c| match c {
+            b'!'..=b'u' => Ok(Some((c - b'!') as u32)),
+            c if c.is_ascii_whitespace() => Ok(None),
+            _ => Err(Error::InvalidAscii85String),
+        }, Some(b'z'))
+    }
+}
+
+impl Encoding for Z85 {
+    fn encode(&self, bytes: &[u8]) -> String {
+        encode85(bytes, |d| Z85_ALPHABET[d as usize], None)
+    }
+
+    fn decode(&self, s: &str) -> Result<Vec<u8>, Error> {
+        decode85(s.as_bytes(), |c| {
+            Z85_ALPHABET.iter()
+                .position(|&x| x == c)
+                .map(|p| Some(p as u32))
+                .ok_or(Error::InvalidAscii85String)
+        }, None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ascii85_works() {
+        let vectors: [(&[u8], &str); 5] = [
+            (b"", ""),
+            (b".", "/c"),
+            (b"Man is distinguished", "9jqo^BlbD-BleB1DJ+*+F(f,q"),
+            (b"\x00\x00\x00\x00abc", "z@:E^"),
+            (b"\x00\x00\x00", "!!!!"),
+        ];
+
+        for (bytes, encoded) in vectors {
+            assert_eq!(Ascii85.encode(bytes), encoded);
+            assert_eq!(Ascii85.decode(encoded).unwrap(), bytes);
+        }
+
+        assert_eq!(Ascii85.decode("<~9jqo^BlbD-\nBleB1DJ+*+F(f,q~>").unwrap(), b"Man is distinguished");
+        assert_eq!(Ascii85.decode("9jqz^"), Err(Error::InvalidAscii85String));
+        assert_eq!(Ascii85.decode("9jqo^B"), Err(Error::InvalidAscii85String));
+        assert_eq!(Ascii85.decode("uuuuu"), Err(Error::InvalidAscii85String));
+        assert_eq!(Ascii85.decode("9jqo~"), Err(Error::InvalidAscii85String));
+    }
+
+    #[test]
+    fn z85_works() {
+        // Test vector from ZeroMQ RFC 32
+        let bytes = [0x86, 0x4F, 0xD2, 0x6F, 0xB5, 0x59, 0xF7, 0x5B];
+        assert_eq!(Z85.encode(&bytes), "HelloWorld");
+        assert_eq!(Z85.decode("HelloWorld").unwrap(), bytes);
+
+        assert_eq!(Z85.encode(&[0; 4]), "00000");
+        assert_eq!(Z85.decode(&Z85.encode(b"abcde")).unwrap(), b"abcde");
+        assert_eq!(Z85.decode("Hello World"), Err(Error::InvalidAscii85String));
+    }
+}
@@ -0,0 +1,172 @@
+use crate::encoding::Encoding;
+use crate::Error;
+
+const RFC4648_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+const CROCKFORD_ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+const PAD: u8 = b'=';
+
+/// The 32 chars used to represent 5 bits groups
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Base32Alphabet {
+    /// RFC 4648 section 6
+    Rfc4648,
+    /// Douglas Crockford's alphabet, without the check symbol
+    Crockford,
+}
+
+/// Base32 encoding.
+///
+/// Decoding is case-insensitive. The Crockford dialect also skips
+/// hyphens and reads 'I'/'L' as 1 and 'O' as 0, as the spec suggests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Base32 {
+    alphabet: Base32Alphabet,
+    padding: bool,
+}
+
+impl Base32 {
+    /// RFC 4648 alphabet with padding
+    pub const RFC4648: Base32 = Base32 { alphabet: Base32Alphabet::Rfc4648, padding: true };
+    /// RFC 4648 alphabet without padding
+    pub const RFC4648_NO_PAD: Base32 = Base32 { alphabet: Base32Alphabet::Rfc4648, padding: false };
+    /// Crockford's alphabet (never padded)
+    pub const CROCKFORD: Base32 = Base32 { alphabet: Base32Alphabet::Crockford, padding: false };
+
+    fn decode_char(&self, c: u8) -> Result<u8, Error> {
+        let c = c.to_ascii_uppercase();
+
+        match self.alphabet {
+            Base32Alphabet::Rfc4648 => match c {
+                b'A'..=b'Z' => Ok(c - b'A'),
+                b'2'..=b'7' => Ok(c - b'2' + 26),
+                _ => Err(Error::InvalidBase32String),
+            },
+            Base32Alphabet::Crockford => match c {
+                b'O' => Ok(0),
+                b'I' | b'L' => Ok(1),
+                c => CROCKFORD_ALPHABET.iter()
+                    .position(|&x| x == c)
+                    .map(|p| p as u8)
+                    .ok_or(Error::InvalidBase32String),
+            },
+        }
+    }
+}
+
+impl Encoding for Base32 {
+    fn encode(&self, bytes: &[u8]) -> String {
+        let chars = match self.alphabet {
+            Base32Alphabet::Rfc4648 => RFC4648_ALPHABET,
+            Base32Alphabet::Crockford => CROCKFORD_ALPHABET,
+        };
+
+        let mut out = Vec::with_capacity(bytes.len().div_ceil(5) * 8);
+        for group in bytes.chunks(5) {
+            let mut buffer = [0u8; 5];
+            buffer[..group.len()].copy_from_slice(group);
+            let value = buffer.iter().fold(0u64, |acc, b| (acc << 8) | *b as u64);
+
+            // Only the chars covering at least a bit of the input are written
+            let n_chars = (group.len() * 8).div_ceil(5);
+            for i in 0..n_chars {
+                out.push(chars[((value >> (35 - 5 * i)) & 0b11111) as usize]);
+            }
+
+            if self.padding {
+                out.resize(out.len() + 8 - n_chars, PAD);
+            }
+        }
+
+        String::from_utf8_lossy(&out).into_owned()
+    }
+
+    fn decode(&self, s: &str) -> Result<Vec<u8>, Error> {
+        let mut input: Vec<u8> = s.bytes().collect();
+        if self.alphabet == Base32Alphabet::Crockford {
+            input.retain(|&c| c != b'-');
+        }
+
+        // Padding, if present, must complete the last group of 8 chars, so
+        // there are exactly `8 - group.len()` pad chars and never a full group
+        let unpadded = input.iter().rposition(|&c| c != PAD).map_or(0, |p| p + 1);
+        let n_pad = input.len() - unpadded;
+        if n_pad > 0 && (!self.padding || n_pad >= 8 || !input.len().is_multiple_of(8)) {
+            return Err(Error::InvalidBase32Padding);
+        }
+        let input = &input[..unpadded];
+
+        let mut out = Vec::with_capacity(input.len() * 5 / 8);
+        for group in input.chunks(8) {
+            // Valid lengths of the last group for 1, 2, 3, 4 (or 5) bytes
+            let n_bytes = match group.len() {
+                2 => 1,
+                4 => 2,
+                5 => 3,
+                7 => 4,
+                8 => 5,
+                _ => return Err(Error::InvalidBase32String),
+            };
+
+            let mut value = 0u64;
+            for (i, c) in group.iter().enumerate() {
+                value |= (self.decode_char(*c)? as u64) << (35 - 5 * i);
+            }
+
+            // The bits after the last decoded byte must be zero
+            if value & ((1 << (40 - 8 * n_bytes)) - 1) != 0 {
+                return Err(Error::NonCanonicalBase32);
+            }
+
+            for i in 0..n_bytes {
+                out.push((value >> (32 - 8 * i)) as u8);
+            }
+        }
+
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rfc4648_works() {
+        // Test vectors from RFC 4648, section 10
+        let vectors = [
+            ("", ""), ("f", "MY======"), ("fo", "MZXQ===="), ("foo", "MZXW6==="),
+            ("foob", "MZXW6YQ="), ("fooba", "MZXW6YTB"), ("foobar", "MZXW6YTBOI======"),
+        ];
+
+        for (plain, encoded) in vectors {
+            assert_eq!(Base32::RFC4648.encode(plain.as_bytes()), encoded);
+            assert_eq!(Base32::RFC4648.decode(encoded).unwrap(), plain.as_bytes());
+            assert_eq!(Base32::RFC4648.decode(&encoded.to_lowercase()).unwrap(), plain.as_bytes());
+            assert_eq!(Base32::RFC4648_NO_PAD.decode(encoded.trim_end_matches('=')).unwrap(), plain.as_bytes());
+        }
+
+        assert_eq!(Base32::RFC4648_NO_PAD.encode(b"foobar"), "MZXW6YTBOI");
+        assert_eq!(Base32::RFC4648.decode("MZXW6==="), Ok(b"foo".to_vec()));
+        assert_eq!(Base32::RFC4648.decode("MZXW6=="), Err(Error::InvalidBase32Padding));
+        assert_eq!(Base32::RFC4648.decode("MZXW6===="), Err(Error::InvalidBase32Padding));
+        assert_eq!(Base32::RFC4648.decode("MY======MZXQ===="), Err(Error::InvalidBase32String));
+        assert_eq!(Base32::RFC4648.decode("MY==============="), Err(Error::InvalidBase32Padding));
+        assert_eq!(Base32::RFC4648.decode("========"), Err(Error::InvalidBase32Padding));
+        assert_eq!(Base32::RFC4648.decode("MZX====="), Err(Error::InvalidBase32String));
+        assert_eq!(Base32::RFC4648.decode("MZXW1==="), Err(Error::InvalidBase32String));
+        assert_eq!(Base32::RFC4648.decode("MZ======"), Err(Error::NonCanonicalBase32));
+        assert_eq!(Base32::RFC4648.decode("MZXW7==="), Err(Error::NonCanonicalBase32));
+        assert_eq!(Base32::RFC4648_NO_PAD.decode("MZXW6==="), Err(Error::InvalidBase32Padding));
+    }
+
+    #[test]
+    fn crockford_works() {
+        assert_eq!(Base32::CROCKFORD.encode(b"foobar"), "CSQPYRK1E8");
+        assert_eq!(Base32::CROCKFORD.decode("CSQPYRK1E8").unwrap(), b"foobar");
+        assert_eq!(Base32::CROCKFORD.decode("csqp-yrki-e8").unwrap(), b"foobar");
+        assert_eq!(Base32::CROCKFORD.decode("CSQPYRKLE8").unwrap(), b"foobar");
+        assert_eq!(Base32::CROCKFORD.decode("CSQPYRK1EU"), Err(Error::InvalidBase32String));
+        assert_eq!(Base32::CROCKFORD.decode("CSQPYRK1E9"), Err(Error::NonCanonicalBase32));
+        assert_eq!(Base32::CROCKFORD.decode("CSQPYRK1E8=="), Err(Error::InvalidBase32Padding));
+    }
+}
@@ -0,0 +1,89 @@
+use crate::encoding::Encoding;
+use crate::Error;
+
+const BITCOIN_ALPHABET: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+/// Base58 encoding with the Bitcoin alphabet.
+///
+/// The input is treated as a big-endian number, each leading zero
+/// byte is encoded as a leading '1'.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Base58;
+
+impl Encoding for Base58 {
+    fn encode(&self, bytes: &[u8]) -> String {
+        let zeros = bytes.iter().take_while(|&&b| b == 0).count();
+
+        // Base58 digits, least significant first
+        let mut digits: Vec<u8> = Vec::with_capacity(bytes.len() * 138 / 100 + 1);
+        for &b in &bytes[zeros..] {
+            let mut carry = b as u32;
+            for d in digits.iter_mut() {
+                carry += (*d as u32) << 8;
+                *d = (carry % 58) as u8;
+                carry /= 58;
+            }
+            while carry > 0 {
+                digits.push((carry % 58) as u8);
+                carry /= 58;
+            }
+        }
+
+        let mut out = vec![BITCOIN_ALPHABET[0]; zeros];
+        out.extend(digits.iter().rev().map(|&d| BITCOIN_ALPHABET[d as usize]));
+
+        String::from_utf8_lossy(&out).into_owned()
+    }
+
+    fn decode(&self, s: &str) -> Result<Vec<u8>, Error> {
+        let input = s.as_bytes();
+        let zeros = input.iter().take_while(|&&c| c == BITCOIN_ALPHABET[0]).count();
+
+        // Output bytes, least significant first
+        let mut bytes: Vec<u8> = Vec::with_capacity(input.len() * 733 / 1000 + 1);
+        for &c in &input[zeros..] {
+            let mut carry = BITCOIN_ALPHABET.iter()
+                .position(|&x| x == c)
+                .ok_or(Error::InvalidBase58String)? as u32;
+
+            for b in bytes.iter_mut() {
+                carry += *b as u32 * 58;
+                *b = carry as u8;
+                carry >>= 8;
+            }
+            while carry > 0 {
+                bytes.push(carry as u8);
+                carry >>= 8;
+            }
+        }
+
+        let mut out = vec![0; zeros];
+        out.extend(bytes.iter().rev());
+
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base58_works() {
+        let vectors: [(&[u8], &str); 6] = [
+            (b"", ""),
+            (b"\x00", "1"),
+            (b"\x00\x00\x01", "112"),
+            (b"Hello World!", "2NEpo7TZRRrLZSi2U"),
+            (b"The quick brown fox jumps over the lazy dog.", "USm3fpXnKG5EUBx2ndxBDMPVciP5hGey2Jh4NDv6gmeo1LkMeiKrLJUUBk6Z"),
+            (&[0x00, 0x00, 0x28, 0x7f, 0xb4, 0xcd], "11233QC4"),
+        ];
+
+        for (bytes, encoded) in vectors {
+            assert_eq!(Base58.encode(bytes), encoded);
+            assert_eq!(Base58.decode(encoded).unwrap(), bytes);
+        }
+
+        assert_eq!(Base58.decode("0OIl"), Err(Error::InvalidBase58String));
+    }
+}
@@ -0,0 +1,639 @@
+use std::io::{self, Read, Write};
+use crate::encoding::Encoding;
+use crate::Error;
+
+const STANDARD_ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+const URL_SAFE_ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+const PAD: u8 = b'=';
+
+/// The 64 chars used to represent sextets
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Alphabet {
+    /// RFC 4648 section 4 ('+' and '/')
+    Standard,
+    /// RFC 4648 section 5 ('-' and '_')
+    UrlSafe,
+}
+
+impl Alphabet {
+    fn chars(&self) -> &'static [u8; 64] {
+        match self {
+            Alphabet::Standard => STANDARD_ALPHABET,
+            Alphabet::UrlSafe => URL_SAFE_ALPHABET,
+        }
+    }
+
+    /// Converts a base64 char to its 6 bits value
+    fn decode_char(&self, c: u8) -> Result<u8, Error> {
+        match (c, self) {
+            (b'A'..=b'Z', _) => Ok(c - b'A'),
+            (b'a'..=b'z', _) => Ok(c - b'a' + 26),
+            (b'0'..=b'9', _) => Ok(c - b'0' + 52),
+            (b'+', Alphabet::Standard) | (b'-', Alphabet::UrlSafe) => Ok(62),
+            (b'/', Alphabet::Standard) | (b'_', Alphabet::UrlSafe) => Ok(63),
+            _ => Err(Error::InvalidBase64String)
+        }
+    }
+}
+
+/// How strictly the input is checked when decoding
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeMode {
+    /// Only the canonical encoding is accepted: padding must match the
+    /// dialect, unused trailing bits must be zero and whitespace is
+    /// rejected. If a line width is set, lines must be broken by "\r\n"
+    /// exactly at that width, as the encoder does.
+    Strict,
+    /// Whitespace is skipped, padding is optional, unused trailing bits
+    /// are ignored and padded quanta can be concatenated.
+    Lenient,
+}
+
+/// A base64 dialect, used to encode and decode base64 strings.
+///
+/// Malformed input (chars outside of the alphabet, a single leftover
+/// char, padding in the wrong place) is always rejected, the decoding
+/// mode decides what to do with the rest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Base64 {
+    alphabet: Alphabet,
+    padding: bool,
+    line_width: Option<usize>,
+    mode: DecodeMode,
+}
+
+impl Base64 {
+    /// Standard alphabet with padding
+    pub const STANDARD: Base64 = Base64::new(Alphabet::Standard);
+    /// Standard alphabet without padding
+    pub const NO_PAD: Base64 = Base64::new(Alphabet::Standard).with_padding(false);
+    /// URL and filename safe alphabet with padding
+    pub const URL_SAFE: Base64 = Base64::new(Alphabet::UrlSafe);
+    /// URL and filename safe alphabet without padding (e.g. JWT)
+    pub const URL_SAFE_NO_PAD: Base64 = Base64::new(Alphabet::UrlSafe).with_padding(false);
+    /// MIME (RFC 2045): standard alphabet, lines of 76 chars
+    pub const MIME: Base64 = Base64::new(Alphabet::Standard).with_line_width(Some(76));
+
+    /// Creates a padded, lenient dialect over the given alphabet
+    pub const fn new(alphabet: Alphabet) -> Self {
+        Base64 { alphabet, padding: true, line_width: None, mode: DecodeMode::Lenient }
+    }
+
+    /// Sets whether '=' padding is written (and, in strict mode, required)
+    pub const fn with_padding(mut self, padding: bool) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    /// Sets the width of the lines, encoded output is wrapped with "\r\n"
+    pub const fn with_line_width(mut self, line_width: Option<usize>) -> Self {
+        self.line_width = line_width;
+        self
+    }
+
+    /// Sets the decoding mode
+    pub const fn with_mode(mut self, mode: DecodeMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Shorthand for `with_mode(DecodeMode::Strict)`
+    pub const fn strict(self) -> Self {
+        self.with_mode(DecodeMode::Strict)
+    }
+
+    /// Shorthand for `with_mode(DecodeMode::Lenient)`
+    pub const fn lenient(self) -> Self {
+        self.with_mode(DecodeMode::Lenient)
+    }
+
+    /// Encodes a group of 1 to 3 bytes into a quantum of up to 4 chars,
+    /// adding '=' padding if needed by the dialect.
+    fn encode_quantum(&self, group: &[u8], out: &mut Vec<u8>) {
+        let chars = self.alphabet.chars();
+        let b1 = group[0];
+        let b2 = group.get(1).copied().unwrap_or(0);
+        let b3 = group.get(2).copied().unwrap_or(0);
+
+        out.push(chars[(b1 >> 2) as usize]);
+        out.push(chars[(((b1 & 0b11) << 4) | (b2 >> 4)) as usize]);
+
+        if group.len() > 1 {
+            out.push(chars[(((b2 & 0b1111) << 2) | (b3 >> 6)) as usize]);
+        } else if self.padding {
+            out.push(PAD);
+        }
+
+        if group.len() > 2 {
+            out.push(chars[(b3 & 0b111111) as usize]);
+        } else if self.padding {
+            out.push(PAD);
+        }
+    }
+
+    /// Encodes bytes into a base64 string
+    pub fn encode(&self, bytes: &[u8]) -> String {
+        let mut encoder = Base64Encoder::with_engine(Vec::with_capacity(bytes.len().div_ceil(3)*4), *self);
+        // Writing to a Vec can't fail
+        encoder.write_all(bytes).unwrap();
+        let out = encoder.finish().unwrap();
+
+        String::from_utf8_lossy(&out).into_owned()
+    }
+
+    /// Decodes a base64 string into bytes
+    pub fn decode(&self, s: &str) -> Result<Vec<u8>, Error> {
+        let mut state = DecodeState::default();
+        let mut out = Vec::with_capacity(s.len()/4*3);
+
+        for c in s.bytes() {
+            state.push(self, c, &mut out)?;
+        }
+        state.finish(self, &mut out)?;
+
+        Ok(out)
+    }
+}
+
+impl Encoding for Base64 {
+    fn encode(&self, bytes: &[u8]) -> String {
+        Base64::encode(self, bytes)
+    }
+
+    fn decode(&self, s: &str) -> Result<Vec<u8>, Error> {
+        Base64::decode(self, s)
+    }
+}
+
+/// Progress of a decoding, used to handle input split at any point
+#[derive(Debug, Default)]
+struct DecodeState {
+    sextets: Vec<u8>,
+    padding: usize,
+    // Set after a padded quantum, which in strict mode must be the last
+    padded: bool,
+    // Chars in the current line, and whether it was just ended by '\r'
+    // or "\r\n", to check the line layout in strict mode
+    column: usize,
+    cr: bool,
+    line_break: bool,
+}
+
+impl DecodeState {
+    /// Checks the position of a char in strict mode, where lines must be
+    /// `width` chars long except the last one. Returns true if `c` is
+    /// part of a line break, which has no other effect.
+    fn check_line_layout(&mut self, width: usize, c: u8) -> Result<bool, Error> {
+        match c {
+            b'\r' if !self.cr && self.column == width => {
+                self.cr = true;
+                Ok(true)
+            }
+            b'\n' if self.cr => {
+                self.cr = false;
+                self.line_break = true;
+                self.column = 0;
+                Ok(true)
+            }
+            b'\r' | b'\n' => Err(Error::NonCanonicalBase64),
+            _ if self.cr || self.column == width => Err(Error::NonCanonicalBase64),
+            _ => {
+                self.line_break = false;
+                self.column += 1;
+                Ok(false)
+            }
+        }
+    }
+
+    /// Decodes a char, emitting bytes when a quantum is complete
+    fn push(&mut self, engine: &Base64, c: u8, out: &mut Vec<u8>) -> Result<(), Error> {
+        let strict = engine.mode == DecodeMode::Strict;
+
+        let skip = match engine.line_width {
+            Some(width) if strict => self.check_line_layout(width, c)?,
+            _ => c.is_ascii_whitespace() && !strict,
+        };
+        if skip {
+            return Ok(());
+        }
+
+        match c {
+            PAD => {
+                // Padding can only replace the last one or two chars
+                if self.sextets.len() < 2 || (strict && !engine.padding) {
+                    return Err(Error::InvalidBase64Padding);
+                }
+                self.padding += 1;
+            }
+            c => {
+                if self.padding > 0 || (strict && self.padded) {
+                    return Err(Error::InvalidBase64Padding);
+                }
+                self.sextets.push(engine.alphabet.decode_char(c)?);
+            }
+        }
+
+        if self.sextets.len() + self.padding == 4 {
+            self.padded = self.padding > 0;
+            self.flush(engine, out)?;
+        }
+
+        Ok(())
+    }
+
+    /// Decodes what remains at the end of the input
+    fn finish(&mut self, engine: &Base64, out: &mut Vec<u8>) -> Result<(), Error> {
+        // The encoder never ends the output with a line break
+        if self.cr || self.line_break {
+            return Err(Error::NonCanonicalBase64);
+        }
+
+        match self.sextets.len() {
+            0 => Ok(()),
+            // A single char can't encode a whole byte
+            1 => Err(Error::InvalidBase64Length),
+            _ if self.padding > 0 => Err(Error::InvalidBase64Padding),
+            _ if engine.mode == DecodeMode::Strict && engine.padding => Err(Error::InvalidBase64Padding),
+            _ => self.flush(engine, out),
+        }
+    }
+
+    /// Decodes the collected 2 to 4 sextets into 1 to 3 bytes
+    fn flush(&mut self, engine: &Base64, out: &mut Vec<u8>) -> Result<(), Error> {
+        let s = &self.sextets;
+
+        // Bits not covering a whole byte must be zero in canonical encodings
+        if engine.mode == DecodeMode::Strict {
+            let unused = match s.len() {
+                2 => s[1] & 0b1111,
+                3 => s[2] & 0b11,
+                _ => 0,
+            };
+            if unused != 0 {
+                return Err(Error::NonCanonicalBase64);
+            }
+        }
+
+        out.push((s[0] << 2) | (s[1] >> 4));
+        if s.len() > 2 {
+            out.push(((s[1] & 0b1111) << 4) | (s[2] >> 2));
+        }
+        if s.len() > 3 {
+            out.push(((s[2] & 0b11) << 6) | s[3]);
+        }
+
+        self.sextets.clear();
+        self.padding = 0;
+        Ok(())
+    }
+}
+
+/// Base64 encoder writing its output to the wrapped writer.
+///
+/// Bytes are encoded as soon as a full group of three is available,
+/// the last (padded) quantum is written by `finish` or when the
+/// encoder is dropped.
+pub struct Base64Encoder<W: Write> {
+    inner: Option<W>,
+    engine: Base64,
+    pending: Vec<u8>,
+    column: usize,
+}
+
+impl<W: Write> Base64Encoder<W> {
+    /// Creates an encoder using the standard dialect
+    pub fn new(inner: W) -> Self {
+        Self::with_engine(inner, Base64::STANDARD)
+    }
+
+    /// Creates an encoder using the given dialect
+    pub fn with_engine(inner: W, engine: Base64) -> Self {
+        Base64Encoder { inner: Some(inner), engine, pending: Vec::with_capacity(3), column: 0 }
+    }
+
+    /// Writes the last quantum and returns the wrapped writer
+    pub fn finish(mut self) -> io::Result<W> {
+        self.write_tail()?;
+        // write_tail leaves the writer in place
+        Ok(self.inner.take().unwrap())
+    }
+
+    /// Appends encoded chars to `out`, breaking lines if needed
+    fn push_wrapped(&mut self, quantum: &[u8], out: &mut Vec<u8>) {
+        for &c in quantum {
+            if let Some(width) = self.engine.line_width {
+                if self.column == width {
+                    out.extend_from_slice(b"\r\n");
+                    self.column = 0;
+                }
+            }
+            out.push(c);
+            self.column += 1;
+        }
+    }
+
+    fn write_tail(&mut self) -> io::Result<()> {
+        if self.inner.is_some() {
+            if !self.pending.is_empty() {
+                let mut quantum = Vec::with_capacity(4);
+                self.engine.encode_quantum(&self.pending, &mut quantum);
+                self.pending.clear();
+
+                let mut out = Vec::with_capacity(6);
+                self.push_wrapped(&quantum, &mut out);
+                self.inner.as_mut().unwrap().write_all(&out)?;
+            }
+            self.inner.as_mut().unwrap().flush()?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for Base64Encoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.inner.is_none() {
+            return Err(io::Error::other("encoder already finished"));
+        }
+
+        // Complete the group left over by the previous call
+        let missing = (3 - self.pending.len()).min(buf.len());
+        self.pending.extend_from_slice(&buf[..missing]);
+        let buf_rest = &buf[missing..];
+
+        let mut quanta = Vec::with_capacity((buf.len() / 3 + 1) * 4);
+        if self.pending.len() == 3 {
+            self.engine.encode_quantum(&self.pending, &mut quanta);
+            self.pending.clear();
+        }
+
+        let mut groups = buf_rest.chunks_exact(3);
+        for group in &mut groups {
+            self.engine.encode_quantum(group, &mut quanta);
+        }
+        self.pending.extend_from_slice(groups.remainder());
+
+        let mut out = Vec::with_capacity(quanta.len() + quanta.len() / 32);
+        self.push_wrapped(&quanta, &mut out);
+
+        // Checked at the beginning
+        self.inner.as_mut().unwrap().write_all(&out)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.inner.as_mut() {
+            Some(inner) => inner.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<W: Write> Drop for Base64Encoder<W> {
+    fn drop(&mut self) {
+        // Errors can't be reported here, call `finish` to handle them
+        let _ = self.write_tail();
+    }
+}
+
+/// Base64 decoder reading its input from the wrapped reader.
+///
+/// With the default (lenient) dialect line breaks are skipped, so wrapped
+/// files can be decoded directly, and a padded quantum may be followed by
+/// another one, which allows reading files made of separately encoded lines.
+pub struct Base64Decoder<R: Read> {
+    inner: R,
+    engine: Base64,
+    state: DecodeState,
+    input: Vec<u8>,
+    output: Vec<u8>,
+    output_pos: usize,
+    eof: bool,
+}
+
+impl<R: Read> Base64Decoder<R> {
+    /// Creates a decoder using the standard dialect
+    pub fn new(inner: R) -> Self {
+        Self::with_engine(inner, Base64::STANDARD)
+    }
+
+    /// Creates a decoder using the given dialect
+    pub fn with_engine(inner: R, engine: Base64) -> Self {
+        Base64Decoder {
+            inner,
+            engine,
+            state: DecodeState::default(),
+            input: vec![0; 4096],
+            output: Vec::new(),
+            output_pos: 0,
+            eof: false,
+        }
+    }
+
+    /// Returns the wrapped reader
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Refills the output buffer, returns false at the end of the input
+    fn fill(&mut self) -> io::Result<bool> {
+        self.output.clear();
+        self.output_pos = 0;
+
+        while self.output.is_empty() {
+            if self.eof {
+                return Ok(false);
+            }
+
+            let n = self.inner.read(&mut self.input)?;
+            if n == 0 {
+                self.eof = true;
+                self.state.finish(&self.engine, &mut self.output)?;
+                continue;
+            }
+
+            for &c in &self.input[..n] {
+                self.state.push(&self.engine, c, &mut self.output)?;
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+impl<R: Read> Read for Base64Decoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        if self.output_pos == self.output.len() && !self.fill()? {
+            return Ok(0);
+        }
+
+        let n = buf.len().min(self.output.len() - self.output_pos);
+        buf[..n].copy_from_slice(&self.output[self.output_pos..self.output_pos + n]);
+        self.output_pos += n;
+
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encoding::tests::OneByteReader;
+
+    fn encode_in_chunks(bytes: &[u8], chunk: usize, engine: Base64) -> String {
+        let mut encoder = Base64Encoder::with_engine(Vec::new(), engine);
+        for c in bytes.chunks(chunk) {
+            encoder.write_all(c).unwrap();
+        }
+
+        String::from_utf8(encoder.finish().unwrap()).unwrap()
+    }
+
+    fn decode(input: &[u8]) -> Result<Vec<u8>, Error> {
+        let mut out = Vec::new();
+        Base64Decoder::new(OneByteReader(input)).read_to_end(&mut out)?;
+        Ok(out)
+    }
+
+    #[test]
+    fn encoder_works() {
+        let bytes = b"Just a little something more than two blocks";
+        let expected = "SnVzdCBhIGxpdHRsZSBzb21ldGhpbmcgbW9yZSB0aGFuIHR3byBibG9ja3M=";
+
+        for chunk in 1..=bytes.len() {
+            assert_eq!(encode_in_chunks(bytes, chunk, Base64::STANDARD), expected);
+        }
+
+        assert_eq!(encode_in_chunks(b"", 1, Base64::STANDARD), "");
+        assert_eq!(encode_in_chunks(b"f", 1, Base64::STANDARD), "Zg==");
+        assert_eq!(encode_in_chunks(b"fo", 1, Base64::STANDARD), "Zm8=");
+    }
+
+    #[test]
+    fn encoder_writes_tail_on_drop() {
+        let mut out = Vec::new();
+        {
+            let mut encoder = Base64Encoder::new(&mut out);
+            encoder.write_all(b"fooba").unwrap();
+        }
+
+        assert_eq!(out, b"Zm9vYmE=");
+    }
+
+    #[test]
+    fn decoder_works() {
+        assert_eq!(decode(b"").unwrap(), b"");
+        assert_eq!(decode(b"Zg==").unwrap(), b"f");
+        assert_eq!(decode(b"Zm8=").unwrap(), b"fo");
+        assert_eq!(decode(b"Zm9vYmFy").unwrap(), b"foobar");
+        assert_eq!(decode(b"Zm9v\nYmFy\r\n").unwrap(), b"foobar");
+        assert_eq!(decode(b"Zg==Zm8=").unwrap(), b"ffo");
+        assert_eq!(decode(b"Zm9vYg").unwrap(), b"foob");
+
+        assert_eq!(decode(b"Zm9vY"), Err(Error::InvalidBase64Length));
+        assert_eq!(decode(b"Zm9v*mFy"), Err(Error::InvalidBase64String));
+        assert_eq!(decode(b"Z==="), Err(Error::InvalidBase64Padding));
+        assert_eq!(decode(b"Zg=a"), Err(Error::InvalidBase64Padding));
+        assert_eq!(decode(b"Zm9=="), Err(Error::InvalidBase64Padding));
+    }
+
+    #[test]
+    fn roundtrip_works() {
+        let bytes: Vec<u8> = (0..=255).cycle().take(10000).collect();
+
+        for engine in [Base64::STANDARD, Base64::NO_PAD, Base64::URL_SAFE, Base64::URL_SAFE_NO_PAD, Base64::MIME] {
+            let encoded = encode_in_chunks(&bytes, 7, engine);
+            assert_eq!(engine.decode(&encoded).unwrap(), bytes);
+            assert_eq!(engine.strict().decode(&encoded).unwrap(), bytes);
+
+            let mut decoded = Vec::new();
+            Base64Decoder::with_engine(OneByteReader(encoded.as_bytes()), engine.strict()).read_to_end(&mut decoded).unwrap();
+            assert_eq!(decoded, bytes);
+        }
+    }
+
+    #[test]
+    fn dialects_work() {
+        let bytes = [0xFB, 0xFF, 0xBF, 0xFE];
+
+        assert_eq!(Base64::STANDARD.encode(&bytes), "+/+//g==");
+        assert_eq!(Base64::NO_PAD.encode(&bytes), "+/+//g");
+        assert_eq!(Base64::URL_SAFE.encode(&bytes), "-_-__g==");
+        assert_eq!(Base64::URL_SAFE_NO_PAD.encode(&bytes), "-_-__g");
+
+        assert_eq!(Base64::URL_SAFE.decode("-_-__g==").unwrap(), bytes);
+        assert_eq!(Base64::URL_SAFE.decode("+/+//g=="), Err(Error::InvalidBase64String));
+        assert_eq!(Base64::STANDARD.decode("-_-__g=="), Err(Error::InvalidBase64String));
+    }
+
+    #[test]
+    fn mime_wrapping_works() {
+        let encoded = Base64::MIME.encode(&[0; 120]);
+        let lines: Vec<&str> = encoded.split("\r\n").collect();
+
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0].len(), 76);
+        assert_eq!(lines[1].len(), 76);
+        assert_eq!(lines[2].len(), 8);
+        assert_eq!(Base64::MIME.encode(&[0; 57]).len(), 76);
+    }
+
+    #[test]
+    fn lenient_decoding_works() {
+        let lenient = Base64::STANDARD;
+
+        assert_eq!(lenient.decode(" Zm9v\tYm\nFy ").unwrap(), b"foobar");
+        assert_eq!(lenient.decode("Zm9vYg").unwrap(), b"foob");
+        assert_eq!(lenient.decode("Zh==").unwrap(), b"f");
+        assert_eq!(lenient.decode("Zg==Zg==").unwrap(), b"ff");
+        assert_eq!(Base64::NO_PAD.decode("Zg==").unwrap(), b"f");
+
+        assert_eq!(lenient.decode("Zm9vY"), Err(Error::InvalidBase64Length));
+        assert_eq!(lenient.decode("Zg==="), Err(Error::InvalidBase64Padding));
+        assert_eq!(lenient.decode("Zg="), Err(Error::InvalidBase64Padding));
+        assert_eq!(lenient.decode("="), Err(Error::InvalidBase64Padding));
+    }
+
+    #[test]
+    fn strict_decoding_works() {
+        let strict = Base64::STANDARD.strict();
+
+        assert_eq!(strict.decode("Zm9vYg==").unwrap(), b"foob");
+        assert_eq!(strict.decode("Zm9vYg"), Err(Error::InvalidBase64Padding));
+        assert_eq!(strict.decode("Zm9v YmFy"), Err(Error::InvalidBase64String));
+        assert_eq!(strict.decode("Zm9v\nYmFy"), Err(Error::InvalidBase64String));
+        assert_eq!(strict.decode("Zh=="), Err(Error::NonCanonicalBase64));
+        assert_eq!(strict.decode("Zm9="), Err(Error::NonCanonicalBase64));
+        assert_eq!(strict.decode("Zg==Zg=="), Err(Error::InvalidBase64Padding));
+
+        assert_eq!(Base64::NO_PAD.strict().decode("Zm9vYg").unwrap(), b"foob");
+        assert_eq!(Base64::NO_PAD.strict().decode("Zm9vYg=="), Err(Error::InvalidBase64Padding));
+        assert_eq!(Base64::MIME.strict().decode("Zm9v\r\nYmFy"), Err(Error::NonCanonicalBase64));
+    }
+
+    #[test]
+    fn strict_decoding_checks_line_layout() {
+        let strict = Base64::STANDARD.with_line_width(Some(4)).strict();
+
+        assert_eq!(strict.decode("Zm9v\r\nYmFy").unwrap(), b"foobar");
+        assert_eq!(strict.decode("Zm9v\r\nYg==").unwrap(), b"foob");
+        assert_eq!(strict.decode("Zm9v\r\nYmFy\r\nZg==").unwrap(), b"foobarf");
+
+        // Breaks at other places, or other than "\r\n", are rejected
+        assert_eq!(strict.decode("Zm9vYmFy"), Err(Error::NonCanonicalBase64));
+        assert_eq!(strict.decode("Zm\r\n9vYmFy"), Err(Error::NonCanonicalBase64));
+        assert_eq!(strict.decode("Zm9v\nYmFy"), Err(Error::NonCanonicalBase64));
+        assert_eq!(strict.decode("Zm9v\r\n\r\nYmFy"), Err(Error::NonCanonicalBase64));
+        assert_eq!(strict.decode("Zm9v\r\nYmFy\r\n"), Err(Error::NonCanonicalBase64));
+        assert_eq!(strict.decode("Zm9v\rYmFy"), Err(Error::NonCanonicalBase64));
+
+        // The streaming decoder checks lines split across reads
+        let mut decoded = Vec::new();
+        let mut decoder = Base64Decoder::with_engine(OneByteReader(b"Zm9v\r\nYmF\r\ny"), strict);
+        assert!(decoder.read_to_end(&mut decoded).is_err());
+    }
+}
@@ -0,0 +1,233 @@
+use std::io::{self, Read, Write};
+use crate::encoding::Encoding;
+use crate::Error;
+
+const LOWER_DIGITS: &[u8; 16] = b"0123456789abcdef";
+const UPPER_DIGITS: &[u8; 16] = b"0123456789ABCDEF";
+
+/// Hex (base16) encoding, decoding accepts both cases
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hex {
+    uppercase: bool,
+}
+
+impl Hex {
+    /// Encodes using lowercase digits
+    pub const LOWER: Hex = Hex { uppercase: false };
+    /// Encodes using uppercase digits
+    pub const UPPER: Hex = Hex { uppercase: true };
+
+    /// Encodes a byte as two hex digits
+    fn encode_byte(&self, b: u8, out: &mut Vec<u8>) {
+        let digits = if self.uppercase { UPPER_DIGITS } else { LOWER_DIGITS };
+        out.push(digits[(b >> 4) as usize]);
+        out.push(digits[(b & 0xF) as usize]);
+    }
+}
+
+impl Encoding for Hex {
+    fn encode(&self, bytes: &[u8]) -> String {
+        let mut hex = Vec::with_capacity(bytes.len()*2);
+        for b in bytes {
+            self.encode_byte(*b, &mut hex);
+        }
+
+        String::from_utf8_lossy(&hex).into_owned()
+    }
+
+    fn decode(&self, s: &str) -> Result<Vec<u8>, Error> {
+        let input_bytes = s.as_bytes();
+
+        if !input_bytes.len().is_multiple_of(2) {
+            return Err(Error::ExpectedEvenLength);
+        }
+
+        let mut out = Vec::with_capacity(input_bytes.len()/2);
+        for pair in input_bytes.chunks_exact(2) {
+            out.push((decode_digit(pair[0])? << 4) | decode_digit(pair[1])?);
+        }
+
+        Ok(out)
+    }
+}
+
+/// Converts a hex digit to its 4 bits value
+fn decode_digit(x: u8) -> Result<u8, Error> {
+    match x {
+        b'0'..=b'9' => Ok(x - b'0'),
+        b'A'..=b'F' => Ok(x - b'A' + 10),
+        b'a'..=b'f' => Ok(x - b'a' + 10),
+        _ => Err(Error::InvalidHexString)
+    }
+}
+
+/// Hex encoder writing its output to the wrapped writer
+pub struct HexEncoder<W: Write> {
+    inner: W,
+    hex: Hex,
+}
+
+impl<W: Write> HexEncoder<W> {
+    /// Creates an encoder writing lowercase digits
+    pub fn new(inner: W) -> Self {
+        Self::with_engine(inner, Hex::LOWER)
+    }
+
+    /// Creates an encoder writing digits in the case chosen by `hex`
+    pub fn with_engine(inner: W, hex: Hex) -> Self {
+        HexEncoder { inner, hex }
+    }
+
+    /// Flushes and returns the wrapped writer
+    pub fn finish(mut self) -> io::Result<W> {
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for HexEncoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut out = Vec::with_capacity(buf.len() * 2);
+        for b in buf {
+            self.hex.encode_byte(*b, &mut out);
+        }
+
+        self.inner.write_all(&out)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Hex decoder reading its input from the wrapped reader.
+///
+/// Whitespace between digits (e.g. line breaks) is skipped.
+pub struct HexDecoder<R: Read> {
+    inner: R,
+    input: Vec<u8>,
+    high: Option<u8>,
+    output: Vec<u8>,
+    output_pos: usize,
+    eof: bool,
+}
+
+impl<R: Read> HexDecoder<R> {
+    pub fn new(inner: R) -> Self {
+        HexDecoder {
+            inner,
+            input: vec![0; 4096],
+            high: None,
+            output: Vec::new(),
+            output_pos: 0,
+            eof: false,
+        }
+    }
+
+    /// Returns the wrapped reader
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Refills the output buffer, returns false at the end of the input
+    fn fill(&mut self) -> io::Result<bool> {
+        self.output.clear();
+        self.output_pos = 0;
+
+        while self.output.is_empty() {
+            if self.eof {
+                return Ok(false);
+            }
+
+            let n = self.inner.read(&mut self.input)?;
+            if n == 0 {
+                self.eof = true;
+                if self.high.is_some() {
+                    return Err(Error::ExpectedEvenLength.into());
+                }
+                continue;
+            }
+
+            for &c in &self.input[..n] {
+                if c.is_ascii_whitespace() {
+                    continue;
+                }
+
+                let nibble = decode_digit(c)?;
+                match self.high.take() {
+                    Some(high) => self.output.push((high << 4) | nibble),
+                    None => self.high = Some(nibble),
+                }
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+impl<R: Read> Read for HexDecoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        if self.output_pos == self.output.len() && !self.fill()? {
+            return Ok(0);
+        }
+
+        let n = buf.len().min(self.output.len() - self.output_pos);
+        buf[..n].copy_from_slice(&self.output[self.output_pos..self.output_pos + n]);
+        self.output_pos += n;
+
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encoding::tests::OneByteReader;
+
+    fn decode(input: &[u8]) -> Result<Vec<u8>, Error> {
+        let mut out = Vec::new();
+        HexDecoder::new(OneByteReader(input)).read_to_end(&mut out)?;
+        Ok(out)
+    }
+
+    #[test]
+    fn encoder_works() {
+        let mut encoder = HexEncoder::new(Vec::new());
+        encoder.write_all(&[0x01, 0x23]).unwrap();
+        encoder.write_all(&[0xAB]).unwrap();
+        encoder.write_all(&[0xFF]).unwrap();
+
+        assert_eq!(encoder.finish().unwrap(), b"0123abff");
+
+        let mut encoder = HexEncoder::with_engine(Vec::new(), Hex::UPPER);
+        encoder.write_all(&[0xAB, 0xCD]).unwrap();
+        assert_eq!(encoder.finish().unwrap(), b"ABCD");
+    }
+
+    #[test]
+    fn hex_encoding_works() {
+        let bytes = [0x01, 0x23, 0x45, 0x67, 0x89, 0xAB, 0xCD, 0xEF];
+
+        assert_eq!(Hex::LOWER.encode(&bytes), "0123456789abcdef");
+        assert_eq!(Hex::UPPER.encode(&bytes), "0123456789ABCDEF");
+        assert_eq!(Hex::LOWER.decode("0123456789ABCDEF").unwrap(), bytes);
+        assert_eq!(Hex::UPPER.decode("0123456789abcdef").unwrap(), bytes);
+        assert_eq!(Hex::LOWER.decode("0g"), Err(Error::InvalidHexString));
+        assert_eq!(Hex::LOWER.decode("012"), Err(Error::ExpectedEvenLength));
+    }
+
+    #[test]
+    fn decoder_works() {
+        assert_eq!(decode(b"0123456789aAbBcCdDeEfF").unwrap(), [0x01, 0x23, 0x45, 0x67, 0x89, 0xAA, 0xBB, 0xCC, 0xDD, 0xEE, 0xFF]);
+        assert_eq!(decode(b"01 23\n4\r\n5").unwrap(), [0x01, 0x23, 0x45]);
+        assert_eq!(decode(b"").unwrap(), b"");
+
+        assert_eq!(decode(b"1234abcXYZ"), Err(Error::InvalidHexString));
+        assert_eq!(decode(b"11223"), Err(Error::ExpectedEvenLength));
+    }
+}
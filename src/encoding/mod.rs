@@ -0,0 +1,63 @@
+mod ascii85;
+mod base32;
+mod base58;
+mod base64;
+mod hex;
+
+pub use self::ascii85::{Ascii85, Z85};
+pub use self::base32::{Base32, Base32Alphabet};
+pub use self::base58::Base58;
+pub use self::base64::{Alphabet, Base64, Base64Encoder, Base64Decoder, DecodeMode};
+pub use self::hex::{Hex, HexEncoder, HexDecoder};
+
+use crate::Error;
+
+/// A textual representation of byte sequences
+pub trait Encoding {
+    /// Converts bytes into their string representation
+    fn encode(&self, bytes: &[u8]) -> String;
+
+    /// Parses a string representation back into bytes
+    fn decode(&self, s: &str) -> Result<Vec<u8>, Error>;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Read};
+    use super::*;
+
+    /// Reader returning a single byte per call, to exercise
+    /// chunk boundaries falling anywhere in the input.
+    pub struct OneByteReader<'a>(pub &'a [u8]);
+
+    impl Read for OneByteReader<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.0.is_empty() || buf.is_empty() {
+                return Ok(0);
+            }
+
+            buf[0] = self.0[0];
+            self.0 = &self.0[1..];
+            Ok(1)
+        }
+    }
+
+    #[test]
+    fn encodings_roundtrip() {
+        let encodings: [&dyn Encoding; 10] = [
+            &Hex::LOWER, &Hex::UPPER, &Base64::STANDARD, &Base64::URL_SAFE_NO_PAD,
+            &Base32::RFC4648, &Base32::RFC4648_NO_PAD, &Base32::CROCKFORD,
+            &Base58, &Ascii85, &Z85,
+        ];
+
+        for len in 0..40 {
+            let bytes: Vec<u8> = (0..len).map(|i| (i * 73 + 11) as u8).collect();
+            let zeros = vec![0u8; len];
+
+            for e in encodings {
+                assert_eq!(e.decode(&e.encode(&bytes)).unwrap(), bytes);
+                assert_eq!(e.decode(&e.encode(&zeros)).unwrap(), zeros);
+            }
+        }
+    }
+}
@@ -0,0 +1,180 @@
+use std::fmt;
+use std::io;
+
+/// Errors returned by the functions in this crate
+#[derive(Debug)]
+pub enum Error {
+    /// An I/O operation failed
+    Io(io::Error),
+    /// The input contains characters that are not hex digits
+    InvalidHexString,
+    /// The hex input has an odd number of digits
+    ExpectedEvenLength,
+    /// The input contains characters outside of the base64 alphabet
+    InvalidBase64String,
+    /// The base64 input ends with a single char, which can't encode a byte
+    InvalidBase64Length,
+    /// The base64 padding is missing, misplaced or not allowed
+    InvalidBase64Padding,
+    /// The unused bits of the last base64 char are not zero
+    NonCanonicalBase64,
+    /// The input is not a valid base32 string
+    InvalidBase32String,
+    /// The base32 padding is misplaced, of the wrong length or not allowed
+    InvalidBase32Padding,
+    /// The unused bits of the last base32 char are not zero
+    NonCanonicalBase32,
+    /// The input is not a valid base58 string
+    InvalidBase58String,
+    /// The input is not a valid Ascii85 (or Z85) string
+    InvalidAscii85String,
+    /// Two sequences that should have the same size differ
+    NotEqualSize,
+    /// The input length is not valid for the requested operation
+    InvalidLength,
+    /// The padding at the end of the input is malformed
+    InvalidPadding(PaddingError),
+    /// The block size is not usable (e.g. zero)
+    InvalidBlockSize(usize),
+    /// The key has an unsupported length
+    InvalidKeyLength(usize),
+    /// The IV has an unsupported length
+    InvalidIvLength(usize),
+    /// The authentication tag has an unsupported length
+    InvalidTagLength(usize),
+    /// The authentication tag doesn't match: the data or the
+    /// parameters are wrong, or were tampered with
+    AuthenticationFailed,
+    /// The underlying crypto backend (openssl) reported an error
+    #[cfg(feature = "openssl")]
+    Backend(openssl::error::ErrorStack),
+    /// The known plaintext (first) is shorter than the key period
+    /// (second), so it can't reveal the whole key
+    KnownPlaintextTooShort(usize, usize),
+    /// No key period stands out in the ciphertext
+    PeriodNotFound,
+    /// A language model file is malformed
+    InvalidModel,
+    /// An error found at the given line (starting from 1) of a file
+    AtLine(usize, Box<Error>),
+}
+
+/// The reasons why a padding can be invalid, so that each check of
+/// an unpadding function can be told apart
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaddingError {
+    /// The last byte, holding the padding length, is zero or larger
+    /// than a block (PKCS#7, ANSI X.923, ISO 10126)
+    InvalidLengthByte(u8),
+    /// The padding bytes are not all equal to the length (PKCS#7)
+    UnequalBytes,
+    /// The padding bytes before the length are not zero (ANSI X.923)
+    NonZeroBytes,
+    /// There is no 0x80 byte before the trailing zeros (ISO/IEC 7816-4)
+    MissingMarker,
+}
+
+impl fmt::Display for PaddingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PaddingError::InvalidLengthByte(n) => write!(f, "invalid length byte {:#04x}", n),
+            PaddingError::UnequalBytes => write!(f, "padding bytes differ from the length"),
+            PaddingError::NonZeroBytes => write!(f, "non-zero padding bytes"),
+            PaddingError::MissingMarker => write!(f, "missing 0x80 marker"),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "I/O error: {}", e),
+            Error::InvalidHexString => write!(f, "invalid hex string"),
+            Error::ExpectedEvenLength => write!(f, "expected an even number of hex digits"),
+            Error::InvalidBase64String => write!(f, "invalid base64 string"),
+            Error::InvalidBase64Length => write!(f, "invalid base64 length"),
+            Error::InvalidBase64Padding => write!(f, "invalid base64 padding"),
+            Error::NonCanonicalBase64 => write!(f, "non-canonical base64 encoding"),
+            Error::InvalidBase32String => write!(f, "invalid base32 string"),
+            Error::InvalidBase32Padding => write!(f, "invalid base32 padding"),
+            Error::NonCanonicalBase32 => write!(f, "non-canonical base32 encoding"),
+            Error::InvalidBase58String => write!(f, "invalid base58 string"),
+            Error::InvalidAscii85String => write!(f, "invalid Ascii85 string"),
+            Error::NotEqualSize => write!(f, "sequences have different sizes"),
+            Error::InvalidLength => write!(f, "invalid input length"),
+            Error::InvalidPadding(e) => write!(f, "invalid padding: {}", e),
+            Error::InvalidBlockSize(n) => write!(f, "invalid block size: {}", n),
+            Error::InvalidKeyLength(n) => write!(f, "invalid key length: {} bytes", n),
+            Error::InvalidIvLength(n) => write!(f, "invalid IV length: {} bytes", n),
+            Error::InvalidTagLength(n) => write!(f, "invalid tag length: {} bytes", n),
+            Error::AuthenticationFailed => write!(f, "authentication failed"),
+            #[cfg(feature = "openssl")]
+            Error::Backend(e) => write!(f, "crypto backend error: {}", e),
+            Error::KnownPlaintextTooShort(n, p) => write!(f, "known plaintext too short: {} bytes for a key period of {}", n, p),
+            Error::PeriodNotFound => write!(f, "no key period found"),
+            Error::InvalidModel => write!(f, "invalid language model"),
+            Error::AtLine(n, e) => write!(f, "line {}: {}", n, e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            #[cfg(feature = "openssl")]
+            Error::Backend(e) => Some(e),
+            Error::AtLine(_, e) => Some(e.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+// io::Error and ErrorStack can't be compared, so we only look
+// at the kind of I/O error and treat backend errors as unequal.
+impl PartialEq for Error {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Error::Io(a), Error::Io(b)) => a.kind() == b.kind(),
+            #[cfg(feature = "openssl")]
+            (Error::Backend(_), Error::Backend(_)) => false,
+            (Error::InvalidPadding(a), Error::InvalidPadding(b)) => a == b,
+            (Error::InvalidBlockSize(a), Error::InvalidBlockSize(b)) => a == b,
+            (Error::InvalidKeyLength(a), Error::InvalidKeyLength(b)) => a == b,
+            (Error::InvalidIvLength(a), Error::InvalidIvLength(b)) => a == b,
+            (Error::InvalidTagLength(a), Error::InvalidTagLength(b)) => a == b,
+            (Error::KnownPlaintextTooShort(a, p), Error::KnownPlaintextTooShort(b, q)) => a == b && p == q,
+            (Error::AtLine(n, a), Error::AtLine(m, b)) => n == m && a == b,
+            _ => std::mem::discriminant(self) == std::mem::discriminant(other),
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        // Our own errors may travel through io::Error (e.g. when
+        // returned by a Read implementation): unwrap them.
+        if e.get_ref().is_some_and(|inner| inner.is::<Error>()) {
+            // We just checked both the presence and the type of the inner error
+            *e.into_inner().unwrap().downcast::<Error>().unwrap()
+        } else {
+            Error::Io(e)
+        }
+    }
+}
+
+impl From<Error> for io::Error {
+    fn from(e: Error) -> Self {
+        match e {
+            Error::Io(e) => e,
+            e => io::Error::new(io::ErrorKind::InvalidData, e),
+        }
+    }
+}
+
+#[cfg(feature = "openssl")]
+impl From<openssl::error::ErrorStack> for Error {
+    fn from(e: openssl::error::ErrorStack) -> Self {
+        Error::Backend(e)
+    }
+}
@@ -0,0 +1,293 @@
+use crate::cipher::{BlockCipher, DefaultAes};
+use crate::crypto::constant_time_eq;
+use crate::ctr::{CounterLayout, Ctr};
+use crate::Error;
+
+/// Multiplies two elements of GF(2^128), with the bit order and the
+/// reduction polynomial (x^128 + x^7 + x^2 + x + 1) used by GCM: the
+/// most significant bit of the integer is the coefficient of x^0.
+/// Runs in constant time.
+pub fn gf128_mul(x: u128, y: u128) -> u128 {
+    const R: u128 = 0xe1 << 120;
+    let mut z = 0;
+    let mut v = y;
+
+    for i in (0..128).rev() {
+        // All ones if the bit is set, without branching on it
+        z ^= v & 0u128.wrapping_sub((x >> i) & 1);
+        v = (v >> 1) ^ (R & 0u128.wrapping_sub(v & 1));
+    }
+
+    z
+}
+
+/// The GHASH universal hash: each 16-byte block of the input is added
+/// to the state, which is then multiplied by the hash key H
+#[derive(Debug, Clone)]
+pub struct Ghash {
+    h: u128,
+    state: u128,
+}
+
+impl Ghash {
+    pub fn new(h: &[u8; 16]) -> Self {
+        Ghash { h: u128::from_be_bytes(*h), state: 0 }
+    }
+
+    /// Hashes `bytes`, padded with zeros to a multiple of 16 bytes
+    pub fn update(&mut self, bytes: &[u8]) {
+        for chunk in bytes.chunks(16) {
+            let mut block = [0; 16];
+            block[..chunk.len()].copy_from_slice(chunk);
+            self.state = gf128_mul(self.state ^ u128::from_be_bytes(block), self.h);
+        }
+    }
+
+    pub fn digest(&self) -> [u8; 16] {
+        self.state.to_be_bytes()
+    }
+}
+
+/// Galois/Counter Mode (NIST SP 800-38D): CTR encryption, plus a tag
+/// authenticating the ciphertext and the associated data. Needs a
+/// cipher with 16-byte blocks.
+#[derive(Debug, Clone)]
+pub struct Gcm<C: BlockCipher> {
+    cipher: C,
+    h: [u8; 16],
+    tag_len: usize,
+}
+
+impl<C: BlockCipher> Gcm<C> {
+    /// `tag_len` can be 16, 15, 14, 13, 12, 8 or 4 bytes, though
+    /// shorter tags are easier to forge
+    pub fn new(cipher: C, tag_len: usize) -> Result<Self, Error> {
+        if cipher.block_size() != 16 {
+            return Err(Error::InvalidBlockSize(cipher.block_size()));
+        }
+        if !matches!(tag_len, 4 | 8 | 12..=16) {
+            return Err(Error::InvalidTagLength(tag_len));
+        }
+
+        let mut h = [0; 16];
+        cipher.encrypt_block(&mut h)?;
+
+        Ok(Gcm { cipher, h, tag_len })
+    }
+
+    pub fn tag_len(&self) -> usize {
+        self.tag_len
+    }
+
+    /// The pre-counter block J0. IVs of 96 bits are used directly,
+    /// others (of any non-zero length) are hashed.
+    fn pre_counter(&self, iv: &[u8]) -> Result<[u8; 16], Error> {
+        match iv.len() {
+            0 => Err(Error::InvalidIvLength(0)),
+            12 => {
+                let mut j0 = [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1];
+                j0[..12].copy_from_slice(iv);
+                Ok(j0)
+            }
+            n => {
+                let mut ghash = Ghash::new(&self.h);
+                ghash.update(iv);
+                ghash.update(&(n as u128 * 8).to_be_bytes());
+                Ok(ghash.digest())
+            }
+        }
+    }
+
+    /// Encrypts the data starting from the counter block after J0
+    fn ctr(&self, j0: &[u8; 16], bytes: &[u8]) -> Result<Vec<u8>, Error> {
+        let ctr = Ctr::new(&self.cipher, j0, CounterLayout::BigEndian32)?;
+        let mut output = bytes.to_vec();
+        ctr.apply_at(16, &mut output)?;
+        Ok(output)
+    }
+
+    fn tag(&self, j0: &[u8; 16], aad: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, Error> {
+        let mut ghash = Ghash::new(&self.h);
+        ghash.update(aad);
+        ghash.update(ciphertext);
+        let lengths = ((aad.len() as u128 * 8) << 64) | (ciphertext.len() as u128 * 8);
+        ghash.update(&lengths.to_be_bytes());
+
+        let mut tag = *j0;
+        self.cipher.encrypt_block(&mut tag)?;
+        tag.iter_mut().zip(ghash.digest()).for_each(|(t, g)| *t ^= g);
+
+        Ok(tag[..self.tag_len].to_vec())
+    }
+
+    /// Encrypts the plaintext and authenticates it together with the
+    /// associated data `aad`. Returns the ciphertext and the tag.
+    pub fn encrypt(&self, iv: &[u8], aad: &[u8], plaintext: &[u8]) -> Result<(Vec<u8>, Vec<u8>), Error> {
+        let j0 = self.pre_counter(iv)?;
+        let ciphertext = self.ctr(&j0, plaintext)?;
+        let tag = self.tag(&j0, aad, &ciphertext)?;
+        Ok((ciphertext, tag))
+    }
+
+    /// Checks the tag and decrypts the ciphertext. Nothing is decrypted
+    /// if the tag doesn't match.
+    pub fn decrypt(&self, iv: &[u8], aad: &[u8], ciphertext: &[u8], tag: &[u8]) -> Result<Vec<u8>, Error> {
+        if tag.len() != self.tag_len {
+            return Err(Error::InvalidTagLength(tag.len()));
+        }
+
+        let j0 = self.pre_counter(iv)?;
+        if !constant_time_eq(&self.tag(&j0, aad, ciphertext)?, tag) {
+            return Err(Error::AuthenticationFailed);
+        }
+
+        self.ctr(&j0, ciphertext)
+    }
+}
+
+/// AES-GCM encryption with a 128, 192 or 256 bit key and a 16-byte
+/// tag, see `Gcm`
+pub fn aes_gcm_encrypt(bytes: &[u8], key: &[u8], iv: &[u8], aad: &[u8]) -> Result<(Vec<u8>, Vec<u8>), Error> {
+    Gcm::new(DefaultAes::new(key)?, 16)?.encrypt(iv, aad, bytes)
+}
+
+/// AES-GCM decryption with a 128, 192 or 256 bit key and a 16-byte
+/// tag. Truncated tags must be asked for explicitly with `Gcm::new`,
+/// so that whoever supplies the tag can't weaken the check.
+pub fn aes_gcm_decrypt(bytes: &[u8], key: &[u8], iv: &[u8], aad: &[u8], tag: &[u8]) -> Result<Vec<u8>, Error> {
+    Gcm::new(DefaultAes::new(key)?, 16)?.decrypt(iv, aad, bytes, tag)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aes::Aes;
+    use crate::cipher::tests::Toy;
+    use crate::crypto::random_bytes;
+    use crate::tools::ToBytes;
+    #[cfg(feature = "openssl")]
+    use openssl::symm::{decrypt_aead, encrypt_aead, Cipher};
+
+    #[test]
+    fn gf128_mul_works() {
+        let one = 1 << 127;
+        let x = 0x66e94bd4ef8a2c3b884cfa59ca342b2e;
+        assert_eq!(gf128_mul(x, one), x);
+        assert_eq!(gf128_mul(one, x), x);
+        assert_eq!(gf128_mul(x, 0), 0);
+        // x^127 * x = x^128 = x^7 + x^2 + x + 1
+        assert_eq!(gf128_mul(1, 1 << 126), 0xe1 << 120);
+        assert_eq!(gf128_mul(x, 0x1234), gf128_mul(0x1234, x));
+    }
+
+    #[test]
+    fn ghash_works() {
+        // Test case 2 of the GCM specification
+        let h: [u8; 16] = "66e94bd4ef8a2c3b884cfa59ca342b2e".parse_hex().unwrap().try_into().unwrap();
+        let mut ghash = Ghash::new(&h);
+        ghash.update(&"0388dace60b6a392f328c2b971b2fe78".parse_hex().unwrap());
+        ghash.update(&(128u128).to_be_bytes());
+        assert_eq!(ghash.digest().to_vec(), "f38cbb1ad69223dcc3457ae5b6b0f885".parse_hex().unwrap());
+    }
+
+    #[test]
+    fn gcm_spec_vectors() {
+        // Test cases 1-6 of the GCM specification (McGrew and Viega):
+        // key, IV, plaintext, AAD, ciphertext, tag
+        let key = "feffe9928665731c6d6a8f9467308308";
+        let plaintext = "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a72\
+                         1c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b39";
+        let aad = "feedfacedeadbeeffeedfacedeadbeefabaddad2";
+        let vectors = [
+            ("00000000000000000000000000000000", "000000000000000000000000", "", "", "",
+             "58e2fccefa7e3061367f1d57a4e7455a"),
+            ("00000000000000000000000000000000", "000000000000000000000000", "00000000000000000000000000000000", "",
+             "0388dace60b6a392f328c2b971b2fe78", "ab6e47d42cec13bdf53a67b21257bddf"),
+            (key, "cafebabefacedbaddecaf888", "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a72\
+                                               1c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b391aafd255", "",
+             "42831ec2217774244b7221b784d0d49ce3aa212f2c02a4e035c17e2329aca12e\
+              21d514b25466931c7d8f6a5aac84aa051ba30b396a0aac973d58e091473f5985",
+             "4d5c2af327cd64a62cf35abd2ba6fab4"),
+            (key, "cafebabefacedbaddecaf888", plaintext, aad,
+             "42831ec2217774244b7221b784d0d49ce3aa212f2c02a4e035c17e2329aca12e\
+              21d514b25466931c7d8f6a5aac84aa051ba30b396a0aac973d58e091",
+             "5bc94fbc3221a5db94fae95ae7121a47"),
+            (key, "cafebabefacedbad", plaintext, aad,
+             "61353b4c2806934a777ff51fa22a4755699b2a714fcdc6f83766e5f97b6c7423\
+              73806900e49f24b22b097544d4896b424989b5e1ebac0f07c23f4598",
+             "3612d2e79e3b0785561be14aaca2fccb"),
+            (key, "9313225df88406e555909c5aff5269aa6a7a9538534f7da1e4c303d2a318a728\
+                   c3c0c95156809539fcf0e2429a6b525416aedbf5a0de6a57a637b39b", plaintext, aad,
+             "8ce24998625615b603a033aca13fb894be9112a5c3a211a8ba262a3cca7e2ca7\
+              01e4a9a4fba43c90ccdcb281d48c7c6fd62875d2aca417034c34aee5",
+             "619cc5aefffe0bfa462af43c1699d050"),
+        ];
+
+        for (key, iv, plaintext, aad, ciphertext, tag) in vectors {
+            let [key, iv, plaintext, aad, ciphertext, tag] = [key, iv, plaintext, aad, ciphertext, tag].map(|s| s.parse_hex().unwrap());
+            let gcm = Gcm::new(Aes::new(&key).unwrap(), 16).unwrap();
+
+            assert_eq!(gcm.encrypt(&iv, &aad, &plaintext).unwrap(), (ciphertext.clone(), tag.clone()));
+            assert_eq!(gcm.decrypt(&iv, &aad, &ciphertext, &tag).unwrap(), plaintext);
+
+            // Truncated tags are prefixes of the full one
+            let short = Gcm::new(Aes::new(&key).unwrap(), 12).unwrap();
+            assert_eq!(short.encrypt(&iv, &aad, &plaintext).unwrap().1, tag[..12]);
+            assert_eq!(short.decrypt(&iv, &aad, &ciphertext, &tag[..12]).unwrap(), plaintext);
+        }
+    }
+
+    #[test]
+    fn tampering_is_detected() {
+        let key = random_bytes(16);
+        let iv = random_bytes(12);
+        let (ciphertext, tag) = aes_gcm_encrypt(b"attack at dawn", &key, &iv, b"header").unwrap();
+        assert_eq!(aes_gcm_decrypt(&ciphertext, &key, &iv, b"header", &tag).unwrap(), b"attack at dawn");
+
+        let mut flipped = ciphertext.clone();
+        flipped[0] ^= 1;
+        assert_eq!(aes_gcm_decrypt(&flipped, &key, &iv, b"header", &tag), Err(Error::AuthenticationFailed));
+        assert_eq!(aes_gcm_decrypt(&ciphertext, &key, &iv, b"Header", &tag), Err(Error::AuthenticationFailed));
+        assert_eq!(aes_gcm_decrypt(&ciphertext, &key, &random_bytes(12), b"header", &tag), Err(Error::AuthenticationFailed));
+        assert_eq!(aes_gcm_decrypt(&ciphertext[1..], &key, &iv, b"header", &tag), Err(Error::AuthenticationFailed));
+
+        let mut flipped = tag.clone();
+        flipped[15] ^= 0x80;
+        assert_eq!(aes_gcm_decrypt(&ciphertext, &key, &iv, b"header", &flipped), Err(Error::AuthenticationFailed));
+        assert_eq!(aes_gcm_decrypt(&ciphertext, &key, &iv, b"header", &flipped[..15]), Err(Error::InvalidTagLength(15)));
+        assert_eq!(aes_gcm_decrypt(&ciphertext, &key, &iv, b"header", &tag[..4]), Err(Error::InvalidTagLength(4)));
+    }
+
+    #[test]
+    fn bad_parameters_are_rejected() {
+        assert!(matches!(Gcm::new(Aes::new(&[0; 16]).unwrap(), 11), Err(Error::InvalidTagLength(11))));
+        assert!(matches!(Gcm::new(Toy([0; 8]), 16), Err(Error::InvalidBlockSize(8))));
+
+        let gcm = Gcm::new(Aes::new(&[0; 16]).unwrap(), 16).unwrap();
+        assert_eq!(gcm.encrypt(&[], b"", b"text"), Err(Error::InvalidIvLength(0)));
+        assert_eq!(gcm.decrypt(&[0; 12], b"", b"text", &[0; 12]), Err(Error::InvalidTagLength(12)));
+        assert_eq!(aes_gcm_decrypt(b"text", &[0; 16], &[0; 12], b"", &[0; 3]), Err(Error::InvalidTagLength(3)));
+    }
+
+    #[test]
+    #[cfg(feature = "openssl")]
+    fn gcm_matches_openssl() {
+        let key = random_bytes(16);
+        let aad = random_bytes(20);
+        let plaintext = random_bytes(100);
+
+        for (iv_len, tag_len) in [(12, 16), (12, 8), (16, 16), (7, 13), (60, 4)] {
+            let iv = random_bytes(iv_len);
+            let gcm = Gcm::new(Aes::new(&key).unwrap(), tag_len).unwrap();
+            let (ciphertext, tag) = gcm.encrypt(&iv, &aad, &plaintext).unwrap();
+
+            let mut expected_tag = vec![0; tag_len];
+            let expected = encrypt_aead(Cipher::aes_128_gcm(), &key, Some(&iv), &aad, &plaintext, &mut expected_tag).unwrap();
+            assert_eq!(ciphertext, expected);
+            assert_eq!(tag, expected_tag);
+
+            let decrypted = decrypt_aead(Cipher::aes_128_gcm(), &key, Some(&iv), &aad, &ciphertext, &tag).unwrap();
+            assert_eq!(decrypted, plaintext);
+        }
+    }
+}
@@ -0,0 +1,200 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+use crate::Error;
+
+// ANSI colors cycled through to highlight groups of repeated blocks
+const COLORS: [&str; 6] = ["\x1b[31m", "\x1b[32m", "\x1b[33m", "\x1b[34m", "\x1b[35m", "\x1b[36m"];
+// Reverse video for differing bytes, distinct from the group colors
+const HIGHLIGHT: &str = "\x1b[7m";
+const RESET: &str = "\x1b[0m";
+
+/// Block-aligned hexdump: each line shows a block with its offset,
+/// hex bytes and ASCII. Blocks appearing more than once are tagged
+/// with the index of their group (e.g. `#1`), which makes ECB
+/// patterns stand out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hexdump {
+    block_size: usize,
+    color: bool,
+}
+
+impl Hexdump {
+    pub fn new(block_size: usize) -> Result<Self, Error> {
+        if block_size == 0 {
+            return Err(Error::InvalidBlockSize(block_size));
+        }
+
+        Ok(Hexdump { block_size, color: false })
+    }
+
+    /// Enables ANSI colors for repeated (or differing) blocks
+    pub fn with_color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Dumps a buffer, one block per line
+    pub fn dump(&self, bytes: &[u8]) -> String {
+        let groups = block_groups(bytes, self.block_size);
+        let mut out = String::new();
+
+        for (i, block) in bytes.chunks(self.block_size).enumerate() {
+            let offset = i * self.block_size;
+            let color = groups[i].map(|g| COLORS[g % COLORS.len()]).filter(|_| self.color);
+
+            write!(out, "{:08x}  ", offset).unwrap();
+            self.write_hex(&mut out, block, color, None);
+            write!(out, " |{}|", ascii(block)).unwrap();
+            if let Some(g) = groups[i] {
+                write!(out, " #{}", g + 1).unwrap();
+            }
+            out.push('\n');
+        }
+
+        out
+    }
+
+    /// Compares two buffers block by block: equal blocks are marked
+    /// with '=', differing ones with '!' and the differing bytes are
+    /// highlighted if colors are enabled.
+    pub fn diff(&self, a: &[u8], b: &[u8]) -> String {
+        let n_blocks = a.len().max(b.len()).div_ceil(self.block_size);
+        let mut out = String::new();
+
+        for i in 0..n_blocks {
+            let offset = i * self.block_size;
+            let block_a = block_at(a, offset, self.block_size);
+            let block_b = block_at(b, offset, self.block_size);
+
+            write!(out, "{:08x}  ", offset).unwrap();
+            self.write_hex(&mut out, block_a, None, Some(block_b));
+            out.push_str(if block_a == block_b { " = " } else { " ! " });
+            self.write_hex(&mut out, block_b, None, Some(block_a));
+            out.truncate(out.trim_end().len());
+            out.push('\n');
+        }
+
+        out
+    }
+
+    /// Writes a block as hex, padded to the full block width. Bytes
+    /// differing from `other` are highlighted in reverse video.
+    fn write_hex(&self, out: &mut String, block: &[u8], color: Option<&str>, other: Option<&[u8]>) {
+        if let Some(c) = color {
+            out.push_str(c);
+        }
+
+        for (j, b) in block.iter().enumerate() {
+            let differs = other.is_some_and(|o| o.get(j) != Some(b));
+            if differs && self.color {
+                write!(out, "{}{:02x}{}", HIGHLIGHT, b, RESET).unwrap();
+            } else {
+                write!(out, "{:02x}", b).unwrap();
+            }
+            out.push(' ');
+        }
+
+        if color.is_some() {
+            out.push_str(RESET);
+        }
+
+        for _ in block.len()..self.block_size {
+            out.push_str("   ");
+        }
+    }
+}
+
+/// Shorthand for a hexdump without colors
+pub fn hexdump(bytes: &[u8], block_size: usize) -> Result<String, Error> {
+    Ok(Hexdump::new(block_size)?.dump(bytes))
+}
+
+/// For each block returns the index of its group of repetitions, or
+/// None if it is unique. Groups are numbered in order of appearance,
+/// a trailing partial block is never considered repeated.
+pub fn repeated_blocks(bytes: &[u8], block_size: usize) -> Result<Vec<Option<usize>>, Error> {
+    if block_size == 0 {
+        return Err(Error::InvalidBlockSize(block_size));
+    }
+
+    Ok(block_groups(bytes, block_size))
+}
+
+fn block_groups(bytes: &[u8], block_size: usize) -> Vec<Option<usize>> {
+    let mut counts: HashMap<&[u8], usize> = HashMap::new();
+    for block in bytes.chunks_exact(block_size) {
+        *counts.entry(block).or_insert(0) += 1;
+    }
+
+    let mut groups: HashMap<&[u8], usize> = HashMap::new();
+    bytes.chunks(block_size)
+        .map(|block| {
+            if counts.get(block).copied().unwrap_or(0) < 2 {
+                return None;
+            }
+            let next = groups.len();
+            Some(*groups.entry(block).or_insert(next))
+        })
+        .collect()
+}
+
+fn block_at(bytes: &[u8], offset: usize, block_size: usize) -> &[u8] {
+    let start = offset.min(bytes.len());
+    let end = (offset + block_size).min(bytes.len());
+    &bytes[start..end]
+}
+
+fn ascii(block: &[u8]) -> String {
+    block.iter()
+        .map(|&b| if b.is_ascii_graphic() || b == b' ' { b as char } else { '.' })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeated_blocks_works() {
+        let bytes = b"AAAABBBBAAAACCCCBBBBAAAA";
+        assert_eq!(repeated_blocks(bytes, 4).unwrap(), [Some(0), Some(1), Some(0), None, Some(1), Some(0)]);
+        assert_eq!(repeated_blocks(b"AAAAAA", 4).unwrap(), [None, None]);
+        assert_eq!(repeated_blocks(b"AAAAAA", 0), Err(Error::InvalidBlockSize(0)));
+    }
+
+    #[test]
+    fn dump_works() {
+        let dump = hexdump(b"YELLOW SUBMARINEYELLOW SUBMARINE\x00\x01", 16).unwrap();
+        let lines: Vec<&str> = dump.lines().collect();
+
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], "00000000  59 45 4c 4c 4f 57 20 53 55 42 4d 41 52 49 4e 45  |YELLOW SUBMARINE| #1");
+        assert_eq!(lines[1], "00000010  59 45 4c 4c 4f 57 20 53 55 42 4d 41 52 49 4e 45  |YELLOW SUBMARINE| #1");
+        assert_eq!(lines[2], format!("00000020  00 01 {} |..|", " ".repeat(42)));
+        assert_eq!(hexdump(b"", 16).unwrap(), "");
+        assert_eq!(hexdump(b"", 0), Err(Error::InvalidBlockSize(0)));
+    }
+
+    #[test]
+    fn colored_dump_works() {
+        let dump = Hexdump::new(2).unwrap().with_color(true).dump(b"aabbaa");
+        assert!(dump.starts_with(&format!("00000000  {}61 61 {}", COLORS[0], RESET)));
+        assert!(dump.contains("00000002  62 62  |bb|\n"));
+    }
+
+    #[test]
+    fn diff_works() {
+        let diff = Hexdump::new(2).unwrap().diff(b"abcdef", b"abcxe");
+        let lines: Vec<&str> = diff.lines().collect();
+
+        assert_eq!(lines, [
+            "00000000  61 62  = 61 62",
+            "00000002  63 64  ! 63 78",
+            "00000004  65 66  ! 65",
+        ]);
+
+        let colored = Hexdump::new(2).unwrap().with_color(true).diff(b"ab", b"ax");
+        assert_eq!(colored, format!("00000000  61 {}62{}  ! 61 {}78{}\n", HIGHLIGHT, RESET, HIGHLIGHT, RESET));
+        assert!(!COLORS.contains(&HIGHLIGHT));
+    }
+}
@@ -0,0 +1,148 @@
+use std::fmt;
+use std::ops::Deref;
+use std::sync::atomic::{compiler_fence, Ordering};
+use crate::crypto::{constant_time_eq, random_bytes};
+use crate::Error;
+
+/// Overwrites bytes with zeros, in a way the compiler can't optimize
+/// away even if they are never read again
+fn zeroize(bytes: &mut [u8]) {
+    for b in bytes.iter_mut() {
+        // b is a valid, aligned reference
+        unsafe { std::ptr::write_volatile(b, 0) };
+    }
+    compiler_fence(Ordering::SeqCst);
+}
+
+/// Defines a fixed-size byte string type, which is checked on creation,
+/// hidden by Debug, compared in constant time and zeroed on drop
+macro_rules! secret_bytes {
+    ($(#[$doc:meta])* $name:ident, $error:path) => {
+        $(#[$doc])*
+        #[derive(Clone)]
+        pub struct $name<const N: usize>([u8; N]);
+
+        impl<const N: usize> $name<N> {
+            /// Fails if `bytes` is not exactly N bytes long
+            pub fn new(bytes: &[u8]) -> Result<Self, Error> {
+                let array = <[u8; N]>::try_from(bytes).map_err(|_| $error(bytes.len()))?;
+                Ok($name(array))
+            }
+
+            pub const fn from_array(bytes: [u8; N]) -> Self {
+                $name(bytes)
+            }
+
+            /// Generates random bytes
+            pub fn random() -> Self {
+                // random_bytes returns exactly N bytes
+                let mut bytes = random_bytes(N);
+                let result = $name(bytes[..].try_into().unwrap());
+                zeroize(&mut bytes);
+                result
+            }
+        }
+
+        impl<const N: usize> From<[u8; N]> for $name<N> {
+            fn from(bytes: [u8; N]) -> Self {
+                $name(bytes)
+            }
+        }
+
+        impl<const N: usize> Deref for $name<N> {
+            type Target = [u8];
+
+            fn deref(&self) -> &[u8] {
+                &self.0
+            }
+        }
+
+        impl<const N: usize> AsRef<[u8]> for $name<N> {
+            fn as_ref(&self) -> &[u8] {
+                &self.0
+            }
+        }
+
+        impl<const N: usize> fmt::Debug for $name<N> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "{}<{}>(redacted)", stringify!($name), N)
+            }
+        }
+
+        impl<const N: usize> PartialEq for $name<N> {
+            fn eq(&self, other: &Self) -> bool {
+                constant_time_eq(&self.0, &other.0)
+            }
+        }
+
+        impl<const N: usize> Eq for $name<N> {}
+
+        impl<const N: usize> Drop for $name<N> {
+            fn drop(&mut self) {
+                zeroize(&mut self.0);
+            }
+        }
+    };
+}
+
+secret_bytes!(
+    /// A key of N bytes, e.g. `Key<16>` for AES-128
+    Key, Error::InvalidKeyLength
+);
+
+secret_bytes!(
+    /// An initialization vector of N bytes, e.g. `Iv<16>` for AES-CBC
+    Iv, Error::InvalidIvLength
+);
+
+secret_bytes!(
+    /// A nonce of N bytes, e.g. `Nonce<12>` for AES-GCM
+    Nonce, Error::InvalidIvLength
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::{aes_cbc_decrypt, aes_cbc_encrypt};
+    use crate::padding::Pkcs7;
+
+    #[test]
+    fn constructors_check_the_size() {
+        assert_eq!(&*Key::<16>::new(b"YELLOW SUBMARINE").unwrap(), b"YELLOW SUBMARINE");
+        assert_eq!(Key::<16>::new(b"YELLOW"), Err(Error::InvalidKeyLength(6)));
+        assert_eq!(Iv::<16>::new(&[0; 4]), Err(Error::InvalidIvLength(4)));
+        assert_eq!(Nonce::<12>::new(&[0; 16]), Err(Error::InvalidIvLength(16)));
+        assert_eq!(Nonce::<12>::new(&[7; 12]).unwrap(), Nonce::from([7; 12]));
+    }
+
+    #[test]
+    fn random_values_differ() {
+        let a = Key::<32>::random();
+        let b = Key::<32>::random();
+        assert_eq!(a.len(), 32);
+        assert_ne!(a, b);
+        assert_eq!(a, a.clone());
+    }
+
+    #[test]
+    fn debug_is_redacted() {
+        let key = Key::from_array(*b"YELLOW SUBMARINE");
+        assert_eq!(format!("{:?}", key), "Key<16>(redacted)");
+        assert_eq!(format!("{:?}", Iv::<16>::random()), "Iv<16>(redacted)");
+    }
+
+    #[test]
+    fn zeroize_works() {
+        let mut bytes = *b"secret";
+        zeroize(&mut bytes);
+        assert_eq!(bytes, [0; 6]);
+    }
+
+    #[test]
+    fn usable_as_bytes() {
+        let key = Key::<16>::random();
+        let iv = Iv::<16>::random();
+        let ciphertext = aes_cbc_encrypt(b"some text", &key, &iv, &Pkcs7).unwrap();
+        assert_eq!(aes_cbc_decrypt(&ciphertext, &key, &iv, &Pkcs7).unwrap(), b"some text");
+    }
+}
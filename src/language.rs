@@ -0,0 +1,530 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::sync::OnceLock;
+use crate::Error;
+
+/// Something able to tell how plausible a plaintext is.
+/// Higher scores are better, and scores of texts of different
+/// lengths must be comparable. Scorers are shared among the
+/// threads of a search, so they must be Sync.
+pub trait Scorer: Sync {
+    fn score(&self, bytes: &[u8]) -> f64;
+}
+
+impl<F: Fn(&[u8]) -> f64 + Sync> Scorer for F {
+    fn score(&self, bytes: &[u8]) -> f64 {
+        self(bytes)
+    }
+}
+
+/// Weights of the components of `LanguageModel::score`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScoreWeights {
+    /// Average log-likelihood of single bytes
+    pub unigram: f64,
+    /// Chi-squared distance of the letter frequencies (subtracted)
+    pub chi_squared: f64,
+    /// Average log-likelihood of letter quadgrams (or the longest
+    /// n-grams known by the model)
+    pub ngram: f64,
+    /// Ratio of non-printable bytes (subtracted)
+    pub printable: f64,
+}
+
+impl ScoreWeights {
+    /// Looks at single bytes only, to be used on text whose bytes
+    /// are not contiguous (e.g. a column of a repeating-key XOR).
+    pub const UNIGRAM_ONLY: ScoreWeights = ScoreWeights { unigram: 1.0, chi_squared: 0.05, ngram: 0.0, printable: 10.0 };
+}
+
+impl Default for ScoreWeights {
+    fn default() -> Self {
+        ScoreWeights { unigram: 1.0, chi_squared: 0.05, ngram: 0.5, printable: 10.0 }
+    }
+}
+
+/// Length of the longest letter n-grams counted when training
+const MAX_NGRAM: usize = 4;
+
+/// Weight of the trained byte distribution, the rest comes from a
+/// prior favoring printable ASCII, so that bytes missing from the
+/// corpus (e.g. rare capitals) aren't as unlikely as binary garbage.
+const BYTE_WEIGHT: f64 = 0.95;
+
+/// Statistical model of a language, working on raw bytes.
+///
+/// Letters are case-folded for chi-squared and n-grams, while the
+/// byte unigrams keep the case so that random capitalization is
+/// penalized. Every log-likelihood is averaged over the input, so
+/// that texts of different lengths get comparable scores.
+///
+/// Models are trained from a plaintext corpus and can be saved to
+/// and loaded from text files, see `res/models`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LanguageModel {
+    name: String,
+    counts: Counts,
+    /// log10 probability of each byte
+    bytes: Vec<f64>,
+    /// Probability of each letter among letters
+    letters: [f64; 26],
+    /// log10 probabilities of letter n-grams, starting from bigrams
+    ngrams: Vec<HashMap<Vec<u8>, f64>>,
+    /// log10 probability of n-grams not in the tables
+    ngram_floors: Vec<f64>,
+    weights: ScoreWeights,
+}
+
+/// Raw counts a model is built from, this is what gets saved
+#[derive(Debug, Clone, PartialEq)]
+struct Counts {
+    bytes: Vec<u64>,
+    /// Counts of letter n-grams, starting from single letters
+    ngrams: Vec<HashMap<Vec<u8>, u64>>,
+}
+
+impl Counts {
+    fn new() -> Self {
+        Counts { bytes: vec![0; 256], ngrams: vec![HashMap::new(); MAX_NGRAM] }
+    }
+}
+
+/// Languages with a model shipped in `res/models`
+pub const BUILTIN_LANGUAGES: [&str; 5] = ["english", "italian", "spanish", "german", "french"];
+
+/// The shipped models, in the order of `BUILTIN_LANGUAGES`
+const BUILTIN_MODELS: [&str; BUILTIN_LANGUAGES.len()] = [
+    include_str!("../res/models/english.model"),
+    include_str!("../res/models/italian.model"),
+    include_str!("../res/models/spanish.model"),
+    include_str!("../res/models/german.model"),
+    include_str!("../res/models/french.model"),
+];
+
+impl LanguageModel {
+    /// Trains a model on a plaintext corpus
+    pub fn train(name: &str, corpus: &[u8]) -> Self {
+        let mut counts = Counts::new();
+
+        for &b in corpus {
+            counts.bytes[b as usize] += 1;
+        }
+
+        let letters = fold_letters(corpus);
+        for (i, table) in counts.ngrams.iter_mut().enumerate() {
+            for g in letters.windows(i + 1) {
+                *table.entry(g.to_vec()).or_insert(0) += 1;
+            }
+        }
+
+        Self::from_counts(name, counts)
+    }
+
+    fn from_counts(name: &str, counts: Counts) -> Self {
+        let total = counts.bytes.iter().sum::<u64>().max(1) as f64;
+        let bytes = counts.bytes.iter()
+            .enumerate()
+            .map(|(b, &c)| {
+                let prior = if is_printable(b as u8) { 0.999 / 98.0 } else { 0.001 / 158.0 };
+                (BYTE_WEIGHT * c as f64 / total + (1.0 - BYTE_WEIGHT) * prior).log10()
+            })
+            .collect();
+
+        // Add-one smoothing, so that no letter is impossible
+        let single = &counts.ngrams[0];
+        let total = single.values().sum::<u64>() as f64 + 26.0;
+        let mut letters = [0.0; 26];
+        for (i, p) in letters.iter_mut().enumerate() {
+            let c = single.get(&[b'a' + i as u8][..]).copied().unwrap_or(0);
+            *p = (c as f64 + 1.0) / total;
+        }
+
+        let mut ngrams = Vec::new();
+        let mut ngram_floors = Vec::new();
+        for table in &counts.ngrams[1..] {
+            let total = table.values().sum::<u64>().max(1) as f64;
+            ngrams.push(table.iter().map(|(g, &c)| (g.clone(), (c as f64 / total).log10())).collect());
+            // Unknown n-grams are assumed rarer than the ones seen once
+            ngram_floors.push((0.1 / total).log10());
+        }
+
+        LanguageModel {
+            name: name.to_string(),
+            counts,
+            bytes,
+            letters,
+            ngrams,
+            ngram_floors,
+            weights: ScoreWeights::default(),
+        }
+    }
+
+    /// Reads a model in the format written by `write_to`
+    pub fn read_from<R: BufRead>(reader: R) -> Result<Self, Error> {
+        let mut name = None;
+        let mut counts = Counts::new();
+
+        for (i, line) in reader.lines().enumerate() {
+            let line = line?;
+            let at_line = |e| Error::AtLine(i + 1, Box::new(e));
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            match fields[..] {
+                ["language", n] => name = Some(n.to_string()),
+                ["byte", b, c] => {
+                    let b: u8 = b.parse().map_err(|_| at_line(Error::InvalidModel))?;
+                    counts.bytes[b as usize] = c.parse().map_err(|_| at_line(Error::InvalidModel))?;
+                }
+                ["ngram", g, c] => {
+                    let valid = g.len() <= MAX_NGRAM && g.bytes().all(|b| b.is_ascii_lowercase());
+                    if !valid {
+                        return Err(at_line(Error::InvalidModel));
+                    }
+                    let c = c.parse().map_err(|_| at_line(Error::InvalidModel))?;
+                    counts.ngrams[g.len() - 1].insert(g.as_bytes().to_vec(), c);
+                }
+                _ => return Err(at_line(Error::InvalidModel)),
+            }
+        }
+
+        let name = name.ok_or(Error::InvalidModel)?;
+        Ok(Self::from_counts(&name, counts))
+    }
+
+    /// Writes the model as text: its name, then one line per byte
+    /// and per letter n-gram with their counts in the corpus.
+    pub fn write_to<W: Write>(&self, mut writer: W) -> Result<(), Error> {
+        writeln!(writer, "language {}", self.name)?;
+
+        for (b, &c) in self.counts.bytes.iter().enumerate() {
+            if c > 0 {
+                writeln!(writer, "byte {} {}", b, c)?;
+            }
+        }
+
+        for table in &self.counts.ngrams {
+            // Most common first, so that files are stable and readable
+            let mut sorted: Vec<_> = table.iter().collect();
+            sorted.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+            for (g, c) in sorted {
+                writeln!(writer, "ngram {} {}", String::from_utf8_lossy(g), c)?;
+            }
+        }
+
+        Ok(writer.flush()?)
+    }
+
+    /// Loads a model from a file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Self::read_from(BufReader::new(File::open(path)?))
+    }
+
+    /// Saves the model to a file
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        self.write_to(BufWriter::new(File::create(path)?))
+    }
+
+    /// Returns the model shipped for one of `BUILTIN_LANGUAGES`.
+    /// Each model is parsed once, and then cloned.
+    pub fn builtin(name: &str) -> Option<Self> {
+        static MODELS: [OnceLock<LanguageModel>; BUILTIN_LANGUAGES.len()] = [const { OnceLock::new() }; BUILTIN_LANGUAGES.len()];
+
+        let index = BUILTIN_LANGUAGES.iter().position(|&l| l == name)?;
+        // Shipped models are checked by the tests
+        let model = MODELS[index].get_or_init(|| Self::read_from(BUILTIN_MODELS[index].as_bytes()).unwrap());
+
+        Some(model.clone())
+    }
+
+    /// Model of English text
+    pub fn english() -> Self {
+        Self::builtin("english").unwrap()
+    }
+
+    /// Model of Italian text
+    pub fn italian() -> Self {
+        Self::builtin("italian").unwrap()
+    }
+
+    /// Model of Spanish text
+    pub fn spanish() -> Self {
+        Self::builtin("spanish").unwrap()
+    }
+
+    /// Model of German text
+    pub fn german() -> Self {
+        Self::builtin("german").unwrap()
+    }
+
+    /// Model of French text
+    pub fn french() -> Self {
+        Self::builtin("french").unwrap()
+    }
+
+    /// Name of the language, as given when training
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the model with different score weights
+    pub fn with_weights(mut self, weights: ScoreWeights) -> Self {
+        self.weights = weights;
+        self
+    }
+
+    pub fn weights(&self) -> ScoreWeights {
+        self.weights
+    }
+
+    /// Length of the longest n-grams known by the model
+    pub fn max_ngram(&self) -> usize {
+        self.ngrams.len() + 1
+    }
+
+    /// Average log10 probability of the bytes
+    pub fn unigram_log_likelihood(&self, bytes: &[u8]) -> f64 {
+        if bytes.is_empty() {
+            return 0.0;
+        }
+
+        bytes.iter().map(|&b| self.bytes[b as usize]).sum::<f64>() / bytes.len() as f64
+    }
+
+    /// Chi-squared statistic of the (case-folded) letter frequencies,
+    /// divided by the number of letters. Lower means closer to the
+    /// language, text without letters gets 1.
+    pub fn chi_squared(&self, bytes: &[u8]) -> f64 {
+        let mut counts = [0usize; 26];
+        for b in bytes {
+            if b.is_ascii_alphabetic() {
+                counts[(b.to_ascii_lowercase() - b'a') as usize] += 1;
+            }
+        }
+
+        let total: usize = counts.iter().sum();
+        if total == 0 {
+            return 1.0;
+        }
+
+        counts.iter()
+            .zip(self.letters)
+            .map(|(&c, expected)| {
+                let observed = c as f64 / total as f64;
+                (observed - expected).powi(2) / expected
+            })
+            .sum()
+    }
+
+    /// Average log10 probability of the n-grams of letters in the text,
+    /// other bytes are skipped. Text with no n-grams gets the lowest
+    /// possible value, None is returned if the model has no n-grams
+    /// of length n.
+    pub fn ngram_log_likelihood(&self, bytes: &[u8], n: usize) -> Option<f64> {
+        if n < 2 || n > self.max_ngram() {
+            return None;
+        }
+
+        let table = &self.ngrams[n - 2];
+        let floor = self.ngram_floors[n - 2];
+        let letters = fold_letters(bytes);
+
+        if letters.len() < n {
+            return Some(floor);
+        }
+
+        let windows = letters.windows(n);
+        let count = windows.len();
+        let sum: f64 = windows.map(|g| table.get(g).copied().unwrap_or(floor)).sum();
+
+        Some(sum / count as f64)
+    }
+
+    /// Combines the single statistics according to the model's weights
+    pub fn score(&self, bytes: &[u8]) -> f64 {
+        let w = &self.weights;
+        let mut score = w.unigram * self.unigram_log_likelihood(bytes)
+            - w.chi_squared * self.chi_squared(bytes)
+            - w.printable * (1.0 - printable_ratio(bytes));
+
+        if w.ngram != 0.0 {
+            // Always Some, max_ngram is a known length
+            score += w.ngram * self.ngram_log_likelihood(bytes, self.max_ngram()).unwrap();
+        }
+
+        score
+    }
+}
+
+impl Scorer for LanguageModel {
+    fn score(&self, bytes: &[u8]) -> f64 {
+        LanguageModel::score(self, bytes)
+    }
+}
+
+/// Returns the model under which the text is most plausible
+pub fn detect_language<'a>(bytes: &[u8], models: &'a [LanguageModel]) -> Option<&'a LanguageModel> {
+    models.iter().max_by(|a, b| a.score(bytes).total_cmp(&b.score(bytes)))
+}
+
+/// Ratio of printable ASCII chars (including tabs and line breaks)
+pub fn printable_ratio(bytes: &[u8]) -> f64 {
+    if bytes.is_empty() {
+        return 1.0;
+    }
+
+    let printable = bytes.iter().filter(|&&b| is_printable(b)).count();
+
+    printable as f64 / bytes.len() as f64
+}
+
+fn is_printable(b: u8) -> bool {
+    (0x20..0x7F).contains(&b) || b == b'\n' || b == b'\r' || b == b'\t'
+}
+
+/// ASCII letters of the text, lowercased, other bytes are skipped
+fn fold_letters(bytes: &[u8]) -> Vec<u8> {
+    bytes.iter()
+        .filter(|b| b.is_ascii_alphabetic())
+        .map(|b| b.to_ascii_lowercase())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::xor_byte;
+
+    #[test]
+    fn printable_ratio_works() {
+        assert_eq!(printable_ratio(b"Hello!\n"), 1.0);
+        assert_eq!(printable_ratio(b"ab\x00\xFF"), 0.5);
+        assert_eq!(printable_ratio(b""), 1.0);
+    }
+
+    #[test]
+    fn chi_squared_works() {
+        let model = LanguageModel::english();
+
+        assert!(model.chi_squared(b"Hi, how are you?") < model.chi_squared(b"zzz qqq xxx"));
+        assert!(model.chi_squared(b"It is the time of the season") < model.chi_squared(b"Ciao, come va?"));
+        assert_eq!(model.chi_squared(b"1234"), 1.0);
+    }
+
+    #[test]
+    fn ngram_log_likelihood_works() {
+        let model = LanguageModel::english();
+
+        for n in 2..=4 {
+            let english = model.ngram_log_likelihood(b"there is the thing", n).unwrap();
+            let garbage = model.ngram_log_likelihood(b"xqzj vkwq zzpf", n).unwrap();
+            assert!(english > garbage);
+        }
+
+        assert_eq!(model.ngram_log_likelihood(b"abc", 5), None);
+        assert_eq!(model.ngram_log_likelihood(b"abc", 1), None);
+        assert_eq!(model.ngram_log_likelihood(b"a b", 4), Some(model.ngram_floors[2]));
+    }
+
+    #[test]
+    fn score_prefers_english() {
+        let model = LanguageModel::english();
+
+        assert!(model.score(b"Hi, how are you?") > model.score("Hola, ¿como estas?".as_bytes()));
+        assert!(model.score(b"Hi, how are you?") > model.score(b"Ciao, come va?"));
+    }
+
+    #[test]
+    fn score_finds_single_byte_xor_key() {
+        let model = LanguageModel::english();
+        let plaintext = b"Cooking MC's like a pound of bacon";
+        let ciphertext = xor_byte(plaintext, 88);
+
+        let best = (0..=255u8)
+            .max_by(|&a, &b| model.score(&xor_byte(&ciphertext, a)).total_cmp(&model.score(&xor_byte(&ciphertext, b))))
+            .unwrap();
+
+        assert_eq!(best, 88);
+    }
+
+    #[test]
+    fn score_is_length_normalized() {
+        let model = LanguageModel::english();
+        let short = b"Now that the party is jumping";
+        let long = short.repeat(5);
+
+        assert!((model.score(short) - model.score(&long)).abs() < 0.5);
+        assert!(model.score(&long) > model.score(&xor_byte(short, 1)));
+    }
+
+    #[test]
+    fn training_works() {
+        let model = LanguageModel::train("test", b"abab ABAB!");
+
+        assert_eq!(model.name(), "test");
+        assert_eq!(model.counts.bytes[b'a' as usize], 2);
+        assert_eq!(model.counts.ngrams[0][&b"a"[..]], 4);
+        assert_eq!(model.counts.ngrams[1][&b"ba"[..]], 3);
+        assert_eq!(model.counts.ngrams[3][&b"abab"[..]], 3);
+        assert!(model.unigram_log_likelihood(b"ab") > model.unigram_log_likelihood(b"cd"));
+        assert!(model.unigram_log_likelihood(b"cd") > model.unigram_log_likelihood(b"\x00\xFF"));
+    }
+
+    #[test]
+    fn models_roundtrip() {
+        let model = LanguageModel::train("test", "Él dijo: «hola, ¿qué tal?»".as_bytes());
+        let mut saved = Vec::new();
+        model.write_to(&mut saved).unwrap();
+
+        assert_eq!(LanguageModel::read_from(&saved[..]).unwrap(), model);
+        for name in BUILTIN_LANGUAGES {
+            assert_eq!(LanguageModel::builtin(name).unwrap().name(), name);
+        }
+        assert_eq!(LanguageModel::builtin("klingon"), None);
+    }
+
+    #[test]
+    fn invalid_models_are_rejected() {
+        assert_eq!(LanguageModel::read_from(&b"byte 32 1\n"[..]), Err(Error::InvalidModel));
+        assert_eq!(
+            LanguageModel::read_from(&b"language x\n\nbyte 256 1\n"[..]),
+            Err(Error::AtLine(3, Box::new(Error::InvalidModel)))
+        );
+        assert_eq!(
+            LanguageModel::read_from(&b"language x\nngram abcde 1\n"[..]),
+            Err(Error::AtLine(2, Box::new(Error::InvalidModel)))
+        );
+        assert_eq!(
+            LanguageModel::read_from(&b"language x\nngram ab\n"[..]),
+            Err(Error::AtLine(2, Box::new(Error::InvalidModel)))
+        );
+    }
+
+    #[test]
+    fn detect_language_works() {
+        let models: Vec<LanguageModel> = BUILTIN_LANGUAGES.iter().filter_map(|n| LanguageModel::builtin(n)).collect();
+        let samples = [
+            ("english", "We will meet at the old bridge when the night falls"),
+            ("italian", "Ci vediamo al vecchio ponte quando scende la notte"),
+            ("spanish", "Nos vemos en el puente viejo cuando caiga la noche"),
+            ("german", "Wir treffen uns an der alten Brücke, wenn die Nacht kommt"),
+            ("french", "Nous nous retrouverons au vieux pont quand la nuit tombera"),
+        ];
+
+        for (name, text) in samples {
+            assert_eq!(detect_language(text.as_bytes(), &models).unwrap().name(), name);
+        }
+        assert_eq!(detect_language(b"abc", &[]), None);
+    }
+
+    #[test]
+    fn closures_are_scorers() {
+        let scorer = |b: &[u8]| b.len() as f64;
+        assert_eq!(Scorer::score(&scorer, b"abc"), 3.0);
+    }
+}
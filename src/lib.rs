@@ -1,4 +1,19 @@
+pub mod aes;
+pub mod analysis;
+pub mod block;
+pub mod cipher;
+pub mod crib;
 pub mod crypto;
+pub mod ctr;
+pub mod encoding;
+pub mod gcm;
+pub mod hexdump;
+pub mod key;
+pub mod language;
+pub mod padding;
+pub mod parallel;
+pub mod stream;
 pub mod tools;
+mod error;
 
-// TODO better error management in libs
\ No newline at end of file
+pub use error::{Error, PaddingError};
@@ -0,0 +1,252 @@
+use crate::crypto::random_bytes;
+use crate::{Error, PaddingError};
+
+/// A padding scheme, which extends data to a whole number of blocks
+/// before encryption, and is removed after decryption
+pub trait Padding {
+    /// Appends the padding to `bytes`
+    fn pad(&self, bytes: &mut Vec<u8>, block_size: usize) -> Result<(), Error>;
+
+    /// Checks the padding at the end of `bytes`, and returns the
+    /// length of the data without it
+    fn unpadded_len(&self, bytes: &[u8], block_size: usize) -> Result<usize, Error>;
+
+    /// Returns a copy of `bytes` without the padding
+    fn unpad(&self, bytes: &[u8], block_size: usize) -> Result<Vec<u8>, Error> {
+        Ok(bytes[..self.unpadded_len(bytes, block_size)?].to_vec())
+    }
+}
+
+/// Number of bytes to add to `len` bytes to reach the next block, for
+/// schemes that always add at least one byte, holding the length
+fn byte_padding_len(len: usize, block_size: usize) -> Result<usize, Error> {
+    if block_size == 0 || block_size > 255 {
+        return Err(Error::InvalidBlockSize(block_size));
+    }
+    Ok(block_size - len % block_size)
+}
+
+/// Checks that padded data is a non-empty whole number of blocks
+fn check_padded(bytes: &[u8], block_size: usize) -> Result<(), Error> {
+    if block_size == 0 {
+        return Err(Error::InvalidBlockSize(0));
+    }
+    if bytes.is_empty() || !bytes.len().is_multiple_of(block_size) {
+        return Err(Error::InvalidLength);
+    }
+    Ok(())
+}
+
+/// Reads the padding length from the last byte
+fn length_byte(bytes: &[u8], block_size: usize) -> Result<usize, Error> {
+    check_padded(bytes, block_size)?;
+    match bytes[bytes.len() - 1] {
+        n if n == 0 || n as usize > block_size => Err(Error::InvalidPadding(PaddingError::InvalidLengthByte(n))),
+        n => Ok(n as usize),
+    }
+}
+
+/// PKCS#7: n bytes of value n
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pkcs7;
+
+impl Padding for Pkcs7 {
+    fn pad(&self, bytes: &mut Vec<u8>, block_size: usize) -> Result<(), Error> {
+        let n = byte_padding_len(bytes.len(), block_size)?;
+        bytes.resize(bytes.len() + n, n as u8);
+        Ok(())
+    }
+
+    fn unpadded_len(&self, bytes: &[u8], block_size: usize) -> Result<usize, Error> {
+        let n = length_byte(bytes, block_size)?;
+        let start = bytes.len() - n;
+        if bytes[start..].iter().any(|&b| b as usize != n) {
+            return Err(Error::InvalidPadding(PaddingError::UnequalBytes));
+        }
+        Ok(start)
+    }
+}
+
+/// ANSI X.923: n-1 zeros, then the byte n
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AnsiX923;
+
+impl Padding for AnsiX923 {
+    fn pad(&self, bytes: &mut Vec<u8>, block_size: usize) -> Result<(), Error> {
+        let n = byte_padding_len(bytes.len(), block_size)?;
+        bytes.resize(bytes.len() + n - 1, 0);
+        bytes.push(n as u8);
+        Ok(())
+    }
+
+    fn unpadded_len(&self, bytes: &[u8], block_size: usize) -> Result<usize, Error> {
+        let n = length_byte(bytes, block_size)?;
+        let start = bytes.len() - n;
+        if bytes[start..bytes.len() - 1].iter().any(|&b| b != 0) {
+            return Err(Error::InvalidPadding(PaddingError::NonZeroBytes));
+        }
+        Ok(start)
+    }
+}
+
+/// ISO 10126: n-1 random bytes, then the byte n
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Iso10126;
+
+impl Padding for Iso10126 {
+    fn pad(&self, bytes: &mut Vec<u8>, block_size: usize) -> Result<(), Error> {
+        let n = byte_padding_len(bytes.len(), block_size)?;
+        bytes.extend(random_bytes(n - 1));
+        bytes.push(n as u8);
+        Ok(())
+    }
+
+    fn unpadded_len(&self, bytes: &[u8], block_size: usize) -> Result<usize, Error> {
+        Ok(bytes.len() - length_byte(bytes, block_size)?)
+    }
+}
+
+/// ISO/IEC 7816-4 (bit padding): the byte 0x80, then zeros up to the
+/// end of the block
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Iso7816;
+
+impl Padding for Iso7816 {
+    fn pad(&self, bytes: &mut Vec<u8>, block_size: usize) -> Result<(), Error> {
+        if block_size == 0 {
+            return Err(Error::InvalidBlockSize(0));
+        }
+        bytes.push(0x80);
+        bytes.resize(bytes.len().next_multiple_of(block_size), 0);
+        Ok(())
+    }
+
+    fn unpadded_len(&self, bytes: &[u8], block_size: usize) -> Result<usize, Error> {
+        check_padded(bytes, block_size)?;
+        let last_block = &bytes[bytes.len() - block_size..];
+        match last_block.iter().rposition(|&b| b != 0) {
+            Some(i) if last_block[i] == 0x80 => Ok(bytes.len() - block_size + i),
+            _ => Err(Error::InvalidPadding(PaddingError::MissingMarker)),
+        }
+    }
+}
+
+/// Zeros up to the end of the block, if the data doesn't already end
+/// there. Trailing zeros of the data itself are removed as well.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ZeroPadding;
+
+impl Padding for ZeroPadding {
+    fn pad(&self, bytes: &mut Vec<u8>, block_size: usize) -> Result<(), Error> {
+        if block_size == 0 {
+            return Err(Error::InvalidBlockSize(0));
+        }
+        bytes.resize(bytes.len().next_multiple_of(block_size), 0);
+        Ok(())
+    }
+
+    fn unpadded_len(&self, bytes: &[u8], block_size: usize) -> Result<usize, Error> {
+        NoPadding.unpadded_len(bytes, block_size)?;
+        let zeros = bytes.iter().rev().take(block_size - 1).take_while(|&&b| b == 0).count();
+        Ok(bytes.len() - zeros)
+    }
+}
+
+/// No padding: the data must already be a whole number of blocks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NoPadding;
+
+impl Padding for NoPadding {
+    fn pad(&self, bytes: &mut Vec<u8>, block_size: usize) -> Result<(), Error> {
+        self.unpadded_len(bytes, block_size).map(|_| ())
+    }
+
+    fn unpadded_len(&self, bytes: &[u8], block_size: usize) -> Result<usize, Error> {
+        if block_size == 0 {
+            return Err(Error::InvalidBlockSize(0));
+        }
+        if !bytes.len().is_multiple_of(block_size) {
+            return Err(Error::InvalidLength);
+        }
+        Ok(bytes.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn padded<P: Padding>(padding: P, bytes: &[u8], block_size: usize) -> Result<Vec<u8>, Error> {
+        let mut bytes = bytes.to_vec();
+        padding.pad(&mut bytes, block_size)?;
+        Ok(bytes)
+    }
+
+    #[test]
+    fn padding_works() {
+        assert_eq!(padded(Pkcs7, b"YELLOW", 8).unwrap(), b"YELLOW\x02\x02");
+        assert_eq!(padded(AnsiX923, b"YELLOW", 8).unwrap(), b"YELLOW\x00\x02");
+        assert_eq!(padded(Iso7816, b"YELLOW", 8).unwrap(), b"YELLOW\x80\x00");
+        assert_eq!(padded(ZeroPadding, b"YELLOW", 8).unwrap(), b"YELLOW\x00\x00");
+        assert_eq!(padded(NoPadding, b"YELLOW", 8), Err(Error::InvalidLength));
+
+        let iso10126 = padded(Iso10126, b"YELLOW", 8).unwrap();
+        assert_eq!(iso10126.len(), 8);
+        assert_eq!(iso10126[..6], *b"YELLOW");
+        assert_eq!(iso10126[7], 2);
+
+        // A whole block is added to aligned data, except by the
+        // schemes which can't tell it from the padding
+        assert_eq!(padded(Pkcs7, b"YELLOW!!", 8).unwrap(), b"YELLOW!!\x08\x08\x08\x08\x08\x08\x08\x08");
+        assert_eq!(padded(AnsiX923, b"YELLOW!!", 8).unwrap(), b"YELLOW!!\x00\x00\x00\x00\x00\x00\x00\x08");
+        assert_eq!(padded(Iso10126, b"YELLOW!!", 8).unwrap().len(), 16);
+        assert_eq!(padded(Iso7816, b"YELLOW!!", 8).unwrap(), b"YELLOW!!\x80\x00\x00\x00\x00\x00\x00\x00");
+        assert_eq!(padded(ZeroPadding, b"YELLOW!!", 8).unwrap(), b"YELLOW!!");
+        assert_eq!(padded(NoPadding, b"YELLOW!!", 8).unwrap(), b"YELLOW!!");
+        assert_eq!(padded(ZeroPadding, b"", 8).unwrap(), b"");
+
+        assert_eq!(padded(Pkcs7, b"YELLOW", 256), Err(Error::InvalidBlockSize(256)));
+        assert_eq!(padded(Iso7816, b"YELLOW", 256).unwrap().len(), 256);
+        assert_eq!(padded(ZeroPadding, b"YELLOW", 0), Err(Error::InvalidBlockSize(0)));
+    }
+
+    #[test]
+    fn unpadding_works() {
+        let schemes: [&dyn Padding; 6] = [&Pkcs7, &AnsiX923, &Iso10126, &Iso7816, &ZeroPadding, &NoPadding];
+        for padding in schemes {
+            for len in [0, 1, 7, 8, 9, 16] {
+                let data = vec![b'A'; len];
+                let mut bytes = data.clone();
+                if padding.pad(&mut bytes, 8).is_ok() {
+                    assert_eq!(padding.unpad(&bytes, 8).unwrap(), data);
+                }
+            }
+        }
+
+        assert_eq!(ZeroPadding.unpad(b"ABC\x00\x00\x00\x00\x00", 8).unwrap(), b"ABC");
+        assert_eq!(ZeroPadding.unpad(b"\x00\x00\x00\x00\x00\x00\x00\x00", 8).unwrap(), b"\x00");
+        assert_eq!(Iso7816.unpad(b"ABC\x80\x00\x80\x00\x00", 8).unwrap(), b"ABC\x80\x00");
+        assert_eq!(Iso10126.unpad(b"ABCDEF\xff\x02", 8).unwrap(), b"ABCDEF");
+    }
+
+    #[test]
+    fn unpadding_errors_are_distinct() {
+        use PaddingError::*;
+
+        assert_eq!(Pkcs7.unpad(b"ABCDEF\x02\x03", 8), Err(Error::InvalidPadding(UnequalBytes)));
+        assert_eq!(Pkcs7.unpad(b"ABCDEF\x02\x00", 8), Err(Error::InvalidPadding(InvalidLengthByte(0))));
+        assert_eq!(Pkcs7.unpad(b"ABCDEF\x02\x09", 8), Err(Error::InvalidPadding(InvalidLengthByte(9))));
+        assert_eq!(AnsiX923.unpad(b"ABCDE\x01\x00\x03", 8), Err(Error::InvalidPadding(NonZeroBytes)));
+        assert_eq!(AnsiX923.unpad(b"ABCDEF\x00\x10", 8), Err(Error::InvalidPadding(InvalidLengthByte(16))));
+        assert_eq!(Iso10126.unpad(b"ABCDEF\x02\x00", 8), Err(Error::InvalidPadding(InvalidLengthByte(0))));
+        assert_eq!(Iso7816.unpad(b"ABCDEF\x01\x00", 8), Err(Error::InvalidPadding(MissingMarker)));
+        assert_eq!(Iso7816.unpad(b"ABCDEFGH\x00\x00\x00\x00\x00\x00\x00\x00", 8), Err(Error::InvalidPadding(MissingMarker)));
+
+        for padding in [&Pkcs7 as &dyn Padding, &AnsiX923, &Iso10126, &Iso7816] {
+            assert_eq!(padding.unpad(b"", 8), Err(Error::InvalidLength));
+            assert_eq!(padding.unpad(b"ABCDEF\x02", 8), Err(Error::InvalidLength));
+        }
+        assert_eq!(ZeroPadding.unpad(b"ABCDEF\x00", 8), Err(Error::InvalidLength));
+        assert_eq!(NoPadding.unpad(b"", 8).unwrap(), b"");
+    }
+}
@@ -0,0 +1,148 @@
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+/// Result of evaluating a candidate in a search
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome<R> {
+    /// The candidate is not a solution
+    Skip,
+    /// A possible solution, to be compared with the others
+    Candidate(R),
+    /// Certainly the solution: the search stops
+    Found(R),
+}
+
+/// Splits searches over a list of candidates among threads
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Engine {
+    threads: usize,
+}
+
+impl Default for Engine {
+    /// Uses as many threads as the available cores
+    fn default() -> Self {
+        Engine::new(thread::available_parallelism().map_or(1, NonZeroUsize::get))
+    }
+}
+
+impl Engine {
+    /// Creates an engine running `threads` threads, at least one.
+    /// With a single thread, searches run in the calling thread.
+    pub fn new(threads: usize) -> Self {
+        Engine { threads: threads.max(1) }
+    }
+
+    pub fn threads(&self) -> usize {
+        self.threads
+    }
+
+    /// Evaluates the candidates and returns the best result. Results
+    /// are combined with `reduce`, which must not depend on the order
+    /// of its arguments (e.g. break ties on a key), since threads
+    /// finish in any order. As soon as a candidate is `Found`, the
+    /// other threads stop and that result is returned.
+    pub fn search<T, R, E, M>(&self, candidates: &[T], eval: E, reduce: M) -> Option<R>
+    where
+        T: Sync,
+        R: Send,
+        E: Fn(&T) -> Outcome<R> + Sync,
+        M: Fn(R, R) -> R + Sync,
+    {
+        // Small chunks balance the work, but cost more synchronization
+        let chunk = (candidates.len() / (self.threads * 4)).max(1);
+        let next = AtomicUsize::new(0);
+        let found = AtomicBool::new(false);
+        let best: Mutex<Option<R>> = Mutex::new(None);
+
+        let merge = |r: Option<R>, other: R| match r {
+            Some(r) => reduce(r, other),
+            None => other,
+        };
+
+        let worker = || {
+            let mut local = None;
+
+            'search: loop {
+                let start = next.fetch_add(chunk, Ordering::Relaxed);
+                if start >= candidates.len() {
+                    break;
+                }
+
+                for c in &candidates[start..(start + chunk).min(candidates.len())] {
+                    if found.load(Ordering::Relaxed) {
+                        return;
+                    }
+
+                    match eval(c) {
+                        Outcome::Skip => {}
+                        Outcome::Candidate(r) => local = Some(merge(local.take(), r)),
+                        Outcome::Found(r) => {
+                            let mut best = best.lock().unwrap();
+                            if !found.swap(true, Ordering::Relaxed) {
+                                *best = Some(r);
+                            }
+                            break 'search;
+                        }
+                    }
+                }
+            }
+
+            // The lock orders this with the store of a found result
+            let mut best = best.lock().unwrap();
+            if let (Some(r), false) = (local, found.load(Ordering::Relaxed)) {
+                *best = Some(merge(best.take(), r));
+            }
+        };
+
+        thread::scope(|s| {
+            for _ in 1..self.threads {
+                s.spawn(worker);
+            }
+            worker();
+        });
+
+        best.into_inner().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn search_finds_the_best() {
+        let candidates: Vec<u32> = (0..1000).collect();
+
+        for threads in [1, 2, 7] {
+            let engine = Engine::new(threads);
+            let best = engine.search(&candidates, |&c| Outcome::Candidate((c * 7919) % 1000), u32::max);
+            assert_eq!(best, Some(999));
+
+            let none = engine.search(&candidates, |_| Outcome::<u32>::Skip, u32::max);
+            assert_eq!(none, None);
+        }
+
+        assert_eq!(Engine::new(4).search(&[] as &[u32], |&c| Outcome::Candidate(c), u32::max), None);
+        assert_eq!(Engine::new(0).threads(), 1);
+        assert!(Engine::default().threads() >= 1);
+    }
+
+    #[test]
+    fn search_stops_when_found() {
+        let candidates: Vec<u32> = (0..100_000).collect();
+        let evaluated = AtomicUsize::new(0);
+        let eval = |&c: &u32| {
+            evaluated.fetch_add(1, Ordering::Relaxed);
+            if c == 10 { Outcome::Found(c) } else { Outcome::Candidate(0) }
+        };
+
+        assert_eq!(Engine::new(1).search(&candidates, eval, u32::max), Some(10));
+        assert_eq!(evaluated.load(Ordering::Relaxed), 11);
+
+        evaluated.store(0, Ordering::Relaxed);
+        assert_eq!(Engine::new(4).search(&candidates, eval, u32::max), Some(10));
+        assert!(evaluated.load(Ordering::Relaxed) < candidates.len());
+    }
+}
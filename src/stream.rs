@@ -0,0 +1,299 @@
+use std::io::{self, Read, Write};
+use std::mem;
+use crate::cipher::BlockCipher;
+use crate::crypto::checked_block_size;
+use crate::padding::Padding;
+use crate::Error;
+
+/// Encrypts in CBC mode everything written to it, and writes the IV
+/// followed by the ciphertext to the inner writer. Only one block is
+/// kept in memory; `finish` must be called at the end to write the
+/// last, padded, block.
+pub struct CbcEncryptor<W: Write, C: BlockCipher, P: Padding> {
+    inner: W,
+    cipher: C,
+    padding: P,
+    /// Last ciphertext block, the IV at first
+    prev: Vec<u8>,
+    /// Plaintext not yet encrypted, less than a block
+    buffer: Vec<u8>,
+    /// A write failed, so the output is missing blocks
+    failed: bool,
+}
+
+impl<W: Write, C: BlockCipher, P: Padding> CbcEncryptor<W, C, P> {
+    /// Writes the IV, which must be as long as a block
+    pub fn new(mut inner: W, cipher: C, iv: &[u8], padding: P) -> Result<Self, Error> {
+        let block_size = checked_block_size(&cipher)?;
+        if iv.len() != block_size {
+            return Err(Error::InvalidIvLength(iv.len()));
+        }
+
+        inner.write_all(iv)?;
+        Ok(CbcEncryptor { inner, cipher, padding, prev: iv.to_vec(), buffer: Vec::with_capacity(block_size), failed: false })
+    }
+
+    /// Encrypts a block in place, chaining it to the previous one
+    fn encrypt_block(&mut self, block: &mut [u8]) -> Result<(), Error> {
+        block.iter_mut().zip(&self.prev).for_each(|(b, p)| *b ^= p);
+        self.cipher.encrypt_block(block)?;
+        self.prev.copy_from_slice(block);
+        Ok(())
+    }
+
+    /// Pads and writes the last block (if the padding makes one), then
+    /// returns the inner writer. Fails if a write failed before.
+    pub fn finish(mut self) -> Result<W, Error> {
+        if self.failed {
+            return Err(io::Error::other("encryption already failed").into());
+        }
+
+        let mut last = mem::take(&mut self.buffer);
+        self.padding.pad(&mut last, self.prev.len())?;
+        for block in last.chunks_exact_mut(self.prev.len()) {
+            self.encrypt_block(block)?;
+        }
+        self.inner.write_all(&last)?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write, C: BlockCipher, P: Padding> Write for CbcEncryptor<W, C, P> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.failed {
+            return Err(io::Error::other("encryption already failed"));
+        }
+
+        let block_size = self.prev.len();
+        let mut block = vec![0; block_size];
+
+        for chunk in buf.chunks(block_size) {
+            let n = chunk.len().min(block_size - self.buffer.len());
+            self.buffer.extend_from_slice(&chunk[..n]);
+
+            // A full block can be encrypted right away, since the
+            // padding only depends on the last partial one
+            if self.buffer.len() == block_size {
+                block.copy_from_slice(&self.buffer);
+                self.buffer.clear();
+                // Part of the input is consumed already, so the stream
+                // can't be resumed
+                let written = self.encrypt_block(&mut block).map_err(io::Error::from)
+                    .and_then(|()| self.inner.write_all(&block));
+                if let Err(e) = written {
+                    self.failed = true;
+                    return Err(e);
+                }
+            }
+            self.buffer.extend_from_slice(&chunk[n..]);
+        }
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Reads the IV and the ciphertext written by a `CbcEncryptor` from
+/// the inner reader, and decrypts them. The last block decrypted is
+/// held back until the end of the input, where its padding is removed.
+pub struct CbcDecryptor<R: Read, C: BlockCipher, P: Padding> {
+    inner: R,
+    cipher: C,
+    padding: P,
+    /// Last ciphertext block, the IV at first
+    prev: Vec<u8>,
+    /// Decrypted block which may be the last one
+    held: Option<Vec<u8>>,
+    /// Plaintext ready to be read, from `pos`
+    output: Vec<u8>,
+    pos: usize,
+    /// The end of the input was reached
+    finished: bool,
+    /// A read failed, so the rest of the stream can't be trusted
+    failed: bool,
+}
+
+impl<R: Read, C: BlockCipher, P: Padding> CbcDecryptor<R, C, P> {
+    /// Reads the IV
+    pub fn new(mut inner: R, cipher: C, padding: P) -> Result<Self, Error> {
+        let mut iv = vec![0; checked_block_size(&cipher)?];
+        inner.read_exact(&mut iv)?;
+        Ok(CbcDecryptor { inner, cipher, padding, prev: iv, held: None, output: Vec::new(), pos: 0, finished: false, failed: false })
+    }
+
+    /// Reads a whole block, unless the input ends first. Returns the
+    /// number of bytes read.
+    fn read_block(&mut self, block: &mut [u8]) -> io::Result<usize> {
+        let mut n = 0;
+        while n < block.len() {
+            match self.inner.read(&mut block[n..]) {
+                Ok(0) => break,
+                Ok(k) => n += k,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(n)
+    }
+
+    /// Decrypts the next block, and makes the previous one readable.
+    /// At the end of the input, the padding of the last block is
+    /// removed instead.
+    fn next_block(&mut self) -> Result<(), Error> {
+        let mut block = vec![0; self.prev.len()];
+        let n = self.read_block(&mut block)?;
+
+        if n == block.len() {
+            let encrypted = block.clone();
+            self.cipher.decrypt_block(&mut block)?;
+            block.iter_mut().zip(&self.prev).for_each(|(b, p)| *b ^= p);
+            self.prev = encrypted;
+            self.output = self.held.replace(block).unwrap_or_default();
+        } else if n > 0 {
+            return Err(Error::InvalidLength);
+        } else {
+            self.finished = true;
+            let mut last = self.held.take().unwrap_or_default();
+            last.truncate(self.padding.unpadded_len(&last, block.len())?);
+            self.output = last;
+        }
+
+        self.pos = 0;
+        Ok(())
+    }
+}
+
+impl<R: Read, C: BlockCipher, P: Padding> Read for CbcDecryptor<R, C, P> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.output.len() {
+            if self.failed {
+                return Err(io::Error::other("decryption already failed"));
+            }
+            if self.finished {
+                return Ok(0);
+            }
+            if let Err(e) = self.next_block() {
+                // Nothing decrypted so far may be read again
+                self.failed = true;
+                self.output.clear();
+                self.pos = 0;
+                return Err(e.into());
+            }
+        }
+
+        let n = buf.len().min(self.output.len() - self.pos);
+        buf[..n].copy_from_slice(&self.output[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cipher::tests::Toy;
+    use crate::aes::Aes;
+    use crate::crypto::{aes_cbc_decrypt, aes_cbc_encrypt, random_bytes};
+    use crate::padding::{NoPadding, Pkcs7};
+    use crate::PaddingError;
+
+    fn encrypt_in_chunks(plaintext: &[u8], key: &[u8], iv: &[u8], chunk: usize) -> Vec<u8> {
+        let mut encryptor = CbcEncryptor::new(Vec::new(), Aes::new(key).unwrap(), iv, Pkcs7).unwrap();
+        for c in plaintext.chunks(chunk) {
+            encryptor.write_all(c).unwrap();
+        }
+        encryptor.finish().unwrap()
+    }
+
+    fn decrypt_in_chunks(ciphertext: &[u8], key: &[u8], chunk: usize) -> Result<Vec<u8>, Error> {
+        let mut decryptor = CbcDecryptor::new(ciphertext, Aes::new(key).unwrap(), Pkcs7)?;
+        let mut plaintext = Vec::new();
+        let mut buffer = vec![0; chunk];
+        loop {
+            match decryptor.read(&mut buffer)? {
+                0 => return Ok(plaintext),
+                n => plaintext.extend_from_slice(&buffer[..n]),
+            }
+        }
+    }
+
+    #[test]
+    fn streams_match_aes_cbc() {
+        let key = random_bytes(16);
+        let iv = random_bytes(16);
+
+        for len in [0, 1, 15, 16, 17, 32, 100] {
+            let plaintext = random_bytes(len);
+            let expected = [iv.clone(), aes_cbc_encrypt(&plaintext, &key, &iv, &Pkcs7).unwrap()].concat();
+
+            for chunk in [1, 5, 16, 33] {
+                let ciphertext = encrypt_in_chunks(&plaintext, &key, &iv, chunk);
+                assert_eq!(ciphertext, expected);
+                assert_eq!(decrypt_in_chunks(&ciphertext, &key, chunk).unwrap(), plaintext);
+            }
+        }
+    }
+
+    #[test]
+    fn large_streams_work() {
+        let key = random_bytes(32);
+        let plaintext = random_bytes(1 << 20);
+
+        let mut encryptor = CbcEncryptor::new(Vec::new(), Aes::new(&key).unwrap(), &[7; 16], Pkcs7).unwrap();
+        io::copy(&mut &plaintext[..], &mut encryptor).unwrap();
+        let ciphertext = encryptor.finish().unwrap();
+        assert_eq!(aes_cbc_decrypt(&ciphertext[16..], &key, &[7; 16], &Pkcs7).unwrap(), plaintext);
+
+        let mut decrypted = Vec::new();
+        CbcDecryptor::new(&ciphertext[..], Aes::new(&key).unwrap(), Pkcs7).unwrap().read_to_end(&mut decrypted).unwrap();
+        assert_eq!(decrypted, plaintext);
+    }
+
+    #[test]
+    fn bad_streams_are_rejected() {
+        let key = random_bytes(16);
+        let ciphertext = encrypt_in_chunks(b"Just a little something more than two blocks", &key, &[0; 16], 7);
+
+        assert_eq!(decrypt_in_chunks(&ciphertext[..60], &key, 8), Err(Error::InvalidLength));
+        assert_eq!(decrypt_in_chunks(&ciphertext[..16], &key, 8), Err(Error::InvalidLength));
+        assert!(matches!(decrypt_in_chunks(&ciphertext[..10], &key, 8), Err(Error::Io(e)) if e.kind() == io::ErrorKind::UnexpectedEof));
+
+        // Flipping the last byte of the IV breaks the padding of a
+        // one-block message
+        let mut one_block = encrypt_in_chunks(b"short", &key, &[0; 16], 7);
+        one_block[15] ^= 0x10;
+        assert_eq!(decrypt_in_chunks(&one_block, &key, 8), Err(Error::InvalidPadding(PaddingError::InvalidLengthByte(0x1b))));
+
+        let toy = Toy(*b"toy key!");
+        assert!(matches!(CbcEncryptor::new(Vec::new(), &toy, &[0; 16], Pkcs7), Err(Error::InvalidIvLength(16))));
+        let toy_stream = CbcEncryptor::new(Vec::new(), &toy, b"8B IV!!!", Pkcs7).unwrap().finish().unwrap();
+        assert_eq!(toy_stream.len(), 16);
+        let toy_stream = CbcEncryptor::new(Vec::new(), &toy, b"8B IV!!!", NoPadding).unwrap().finish().unwrap();
+        assert_eq!(toy_stream.len(), 8);
+    }
+
+    #[test]
+    fn errors_are_final() {
+        let key = random_bytes(16);
+        let ciphertext = encrypt_in_chunks(b"0123456789abcdef and then some more", &key, &[0; 16], 16);
+        let mut decryptor = CbcDecryptor::new(&ciphertext[..53], Aes::new(&key).unwrap(), Pkcs7).unwrap();
+        let mut buffer = [0; 16];
+
+        assert_eq!(decryptor.read(&mut buffer).unwrap(), 16);
+        assert_eq!(&buffer, b"0123456789abcdef");
+        assert_eq!(Error::from(decryptor.read(&mut buffer).unwrap_err()), Error::InvalidLength);
+        assert!(decryptor.read(&mut buffer).is_err());
+        assert!(decryptor.read(&mut buffer).is_err());
+
+        // Room for the IV and two blocks only
+        let mut output = [0; 24];
+        let mut encryptor = CbcEncryptor::new(&mut output[..], Toy(*b"toy key!"), b"8B IV!!!", Pkcs7).unwrap();
+        assert_eq!(encryptor.write(b"three blocks of text....").unwrap_err().kind(), io::ErrorKind::WriteZero);
+        assert!(encryptor.write(b"more").is_err());
+        assert!(encryptor.finish().is_err());
+    }
+}
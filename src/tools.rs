@@ -1,13 +1,7 @@
-use std::io::{BufReader, BufRead};
+use std::io::{BufReader, BufRead, Read};
 use std::fs::File;
-
-#[derive(Debug, PartialEq)]
-pub enum Error {
-    InvalidHexStringError,
-    InvalidBase64StringError,
-    NotEqualSizeError,
-    ExpectedEvenLength
-}
+use crate::encoding::{Base64, Base64Decoder, Encoding, Hex};
+use crate::Error;
 
 /// Trait with conversions to string representations
 pub trait AsString {
@@ -16,71 +10,22 @@ pub trait AsString {
 
     /// Converts into base64 representation
     fn as_base64(&self) -> String;
+
+    /// Converts into the representation defined by `encoding`
+    fn as_encoding(&self, encoding: &dyn Encoding) -> String;
 }
 
 impl AsString for [u8] {
     fn as_hex(&self) -> String {
-        let mut result = "".to_owned();
-        for n in self {
-            result.push_str(&format!("{:02x}", n));
-        }
-
-        result
+        Hex::LOWER.encode(self)
     }
 
     fn as_base64(&self) -> String {
-        // Encode in base64
-        let mut base64: Vec<u8> = Vec::with_capacity(self.len()/3*4);
-        let mut iter = self.iter();
-
-        // Base64 is encoded in groups of three bytes, if the
-        // length of the byte vector is not a multiple of 3,
-        // it will be necessary to add '=' padding at the end.
-        loop {
-            if let Some(b1) = iter.next() {
-                base64.push(b1 >> 2);
-                base64.push((b1 & 0b11) << 4);
-            } else {
-                break;
-            }
-
-            if let Some(b2) = iter.next() {
-                let last = base64.pop().unwrap();   // We're sure there's 
-                                                    // at least an element
-                base64.push(last | (b2 >> 4));
-                base64.push((b2 & 0b1111) << 2);
-            } else {
-                // Padding
-                base64.push(64);
-                base64.push(64);
-                break;
-            }
-
-            if let Some(b3) = iter.next() {
-                let last = base64.pop().unwrap();   // We're sure there's 
-                                                    // at least an element
-                base64.push(last | (b3 >> 6));
-                base64.push(b3 & 0b111111);
-            } else {
-                // Padding
-                base64.push(64);
-                break;
-            }
-        }
-
-        // Convert to string
-        for b in &mut base64 {
-            *b = match b {
-                0..=25  => 'A' as u8 + *b,
-                26..=51 => 'a' as u8 + (*b - 26),
-                52..=61 => '0' as u8 + (*b - 52),
-                62      => '+' as u8,
-                63      => '/' as u8,
-                64..    => '=' as u8
-            };
-        }
+        Base64::STANDARD.encode(self)
+    }
 
-        String::from_utf8_lossy(&base64).into_owned()
+    fn as_encoding(&self, encoding: &dyn Encoding) -> String {
+        encoding.encode(self)
     }
 }
 
@@ -91,99 +36,102 @@ pub trait ToBytes {
 
     /// Parse a base64 to bytes
     fn parse_base64(&self) -> Result<Vec<u8>, Error>;
+
+    /// Parse a string in the representation defined by `encoding`
+    fn parse_encoding(&self, encoding: &dyn Encoding) -> Result<Vec<u8>, Error>;
 }
 
 impl ToBytes for str {
     /// Convert string representing an hex number to its bytes
     fn parse_hex(&self) -> Result<Vec<u8>, Error> {
-        let input_bytes = self.as_bytes();
-
-        if input_bytes.len() % 2 != 0 {
-            return Err(Error::ExpectedEvenLength);
-        }
-
-        let mut out = Vec::new();
-
-        let get4bits = |x: u8| -> Result<u8, Error> {
-            match x {
-                48..=57 =>  Ok(x - 48), // '0' to '9' -> 0 to 9 
-                65..=70 =>  Ok(x - 55), // 'A' to 'F' -> 10 to 15
-                97..=102 => Ok(x - 87), // 'a' to 'f' -> 10 to 15
-                _ => Err(Error::InvalidHexStringError)
-            }
-        };
-
-        for i in (0..input_bytes.len()).step_by(2) {
-            let b1 = get4bits(input_bytes[i])?;
-
-            // We're sure the next element exists because we 
-            // already checked that the input bytes are even
-            let b2 = get4bits(*input_bytes.get(i+1).unwrap())?;
-            out.push((b1 << 4) | b2);
-        }
-
-        Ok(out)
+        Hex::LOWER.decode(self)
     }
 
     /// Takes as input a base64 string and returns the corresponding
-    /// byte vector
+    /// byte vector. Whitespace is ignored and padding is optional, use
+    /// `Base64` directly for other dialects or strict decoding.
     fn parse_base64(&self) -> Result<Vec<u8>, Error> {
-        let convert = |x| -> Result<u8, Error> {
-            match x {
-                'A'..='Z' => Ok(x as u8 - 65),
-                'a'..='z' => Ok(x as u8 - 71),
-                '0'..='9' => Ok(x as u8 + 4),
-                '+'       => Ok(62),
-                '/'       => Ok(63),
-                _         => Err(Error::InvalidBase64StringError)
-            }
-        };
-
-        let chars: Vec<char> = self.trim_end_matches('=').chars().collect();
-        let mut bytes: Vec<u8> = Vec::with_capacity(self.len()/4*3);
-
-        for i in (0..chars.len()).step_by(4) {
-            let b1 = convert(chars[i])?;
-
-            if let Some(c2) = chars.get(i+1) {
-                let b2 = convert(*c2)?;
-                bytes.push((b1 << 2) | (b2 >> 4));
-
-                if let Some(c3) = chars.get(i+2) {
-                    let b3 = convert(*c3)?;
-                    bytes.push(((b2 & 0b1111) << 4) | (b3 >> 2));
-
-                    if let Some(c4) = chars.get(i+3) {
-                        let b4 = convert(*c4)?;
-                        bytes.push(((b3 & 0b11) << 6) | b4);
-                    } else {
-                        break;
-                    }
-                } else {
-                    break;
-                }
-            } else {
-                bytes.push(b1 << 2);
-                break;
-            }
-        }
+        Base64::STANDARD.decode(self)
+    }
 
-        Ok(bytes)
+    fn parse_encoding(&self, encoding: &dyn Encoding) -> Result<Vec<u8>, Error> {
+        encoding.decode(self)
     }
 }
 
 // Loads a base64 encoded file into a bytes vec
-pub fn load_base64_file(filename: &str) -> Vec<u8> {
-    let file = File::open(filename).unwrap();
-    let reader = BufReader::new(file);
+pub fn load_base64_file(filename: &str) -> Result<Vec<u8>, Error> {
+    let file = File::open(filename)?;
+    let mut decoder = Base64Decoder::new(BufReader::new(file));
     let mut bytes = Vec::new();
 
-    for line in reader.lines() {
-        let mut line_bytes = line.unwrap().parse_base64().unwrap();
-        bytes.append(&mut line_bytes);
+    decoder.read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// Encoding of the lines of a ciphertext file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineEncoding {
+    Hex,
+    Base64,
+    /// Hex if every line is made of an even number of hex
+    /// digits, base64 otherwise
+    Auto,
+}
+
+/// Guesses the encoding of a set of lines. A base64 line could be
+/// made of hex digits only by chance, so the whole set is looked at.
+pub fn detect_encoding<S: AsRef<str>>(lines: &[S]) -> LineEncoding {
+    let is_hex = |l: &str| l.len().is_multiple_of(2) && l.bytes().all(|c| c.is_ascii_hexdigit());
+
+    if lines.iter().all(|l| is_hex(l.as_ref())) {
+        LineEncoding::Hex
+    } else {
+        LineEncoding::Base64
     }
+}
 
-    bytes
+/// Loads a file of encoded ciphertexts, one per line, and returns
+/// them decoded. Blank lines are skipped, decoding errors report the
+/// number of the line they were found at.
+pub fn load_lines(filename: &str, encoding: LineEncoding) -> Result<Vec<Vec<u8>>, Error> {
+    Ok(load_numbered_lines(filename, encoding)?.into_iter().map(|(_, line)| line).collect())
+}
+
+/// Same as `load_lines`, but each ciphertext comes with the number
+/// (starting at 1) of its line in the file
+pub fn load_numbered_lines(filename: &str, encoding: LineEncoding) -> Result<Vec<(usize, Vec<u8>)>, Error> {
+    let file = File::open(filename)?;
+    let mut lines = Vec::new();
+
+    for (n, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if !line.is_empty() {
+            lines.push((n + 1, line.to_owned()));
+        }
+    }
+
+    let encoding = match encoding {
+        LineEncoding::Auto => detect_encoding(&lines.iter().map(|(_, l)| l).collect::<Vec<_>>()),
+        e => e,
+    };
+
+    lines.iter()
+        .map(|(n, line)| {
+            let decoded = match encoding {
+                LineEncoding::Hex => line.parse_hex(),
+                _ => line.parse_base64(),
+            };
+            decoded.map(|d| (*n, d)).map_err(|e| Error::AtLine(*n, Box::new(e)))
+        })
+        .collect()
+}
+
+/// Loads a file of encoded lines and concatenates them, as for
+/// a single ciphertext wrapped on multiple lines.
+pub fn load_file(filename: &str, encoding: LineEncoding) -> Result<Vec<u8>, Error> {
+    Ok(load_lines(filename, encoding)?.concat())
 }
 
 
@@ -191,7 +139,7 @@ pub fn load_base64_file(filename: &str) -> Vec<u8> {
 /// two equally-sized byte sequences.
 pub fn hamming_distance(a: &[u8], b: &[u8]) -> Result<u32, Error> {
     if a.len() != b.len() {
-        return Err(Error::NotEqualSizeError);
+        return Err(Error::NotEqualSize);
     }
     
     let mut dist = 0;
@@ -204,6 +152,8 @@ pub fn hamming_distance(a: &[u8], b: &[u8]) -> Result<u32, Error> {
 
 /// Returns a score for the argument string based on
 /// frequencies of characters in the English language.
+/// See `language::LanguageModel` for a scorer working on bytes.
+#[allow(clippy::approx_constant)] // 'S' => 6.28 is a frequency, not TAU
 pub fn english_score(s: &str) -> f64 {
     let mut score = 0f64;
 
@@ -233,7 +183,7 @@ mod tests {
             "0123456789aAbBcCdDeEfF".parse_hex().unwrap(),
             [0x01, 0x23, 0x45, 0x67, 0x89, 0xAA, 0xBB, 0xCC, 0xDD, 0xEE, 0xFF]
         );
-        assert_eq!("1234abcXYZ".parse_hex(), Err(Error::InvalidHexStringError));
+        assert_eq!("1234abcXYZ".parse_hex(), Err(Error::InvalidHexString));
         assert_eq!("11223".parse_hex(), Err(Error::ExpectedEvenLength));
     }
 
@@ -246,6 +196,10 @@ mod tests {
         assert_eq!("Zm9vYg==".parse_base64().unwrap(), b"foob");
         assert_eq!("Zm9vYmE=".parse_base64().unwrap(), b"fooba");
         assert_eq!("Zm9vYmFy".parse_base64().unwrap(), b"foobar");
+
+        assert_eq!("Zm9v\nYmFy".parse_base64().unwrap(), b"foobar");
+        assert_eq!("Zm9vY".parse_base64(), Err(Error::InvalidBase64Length));
+        assert_eq!("Zg===".parse_base64(), Err(Error::InvalidBase64Padding));
     }
 
     #[test]
@@ -268,13 +222,65 @@ mod tests {
     }
 
 
+    #[test]
+    fn load_base64_file_works() {
+        let bytes = load_base64_file("./res/s01e07").unwrap();
+        assert!(bytes.len().is_multiple_of(16));
+        assert!(matches!(load_base64_file("./res/does-not-exist"), Err(Error::Io(_))));
+    }
+
+    #[test]
+    fn encoding_conversions_work() {
+        use crate::encoding::{Base32, Base58};
+
+        assert_eq!(b"foobar".as_encoding(&Base32::RFC4648), "MZXW6YTBOI======");
+        assert_eq!("MZXW6YTBOI======".parse_encoding(&Base32::RFC4648).unwrap(), b"foobar");
+        assert_eq!(b"Hello World!".as_encoding(&Base58), "2NEpo7TZRRrLZSi2U");
+        assert_eq!("2NEpo7TZRRrLZSi2U".parse_encoding(&Base58).unwrap(), b"Hello World!");
+    }
+
+    #[test]
+    fn load_lines_works() {
+        let hex = load_lines("./res/s01e04", LineEncoding::Auto).unwrap();
+        assert_eq!(hex, load_lines("./res/s01e04", LineEncoding::Hex).unwrap());
+        assert_eq!(hex.len(), 327);
+        assert!(hex.iter().all(|l| l.len() == 29 || l.len() == 30));
+
+        let base64 = load_file("./res/s01e06", LineEncoding::Auto).unwrap();
+        assert_eq!(base64, load_base64_file("./res/s01e06").unwrap());
+    }
+
+    #[test]
+    fn load_lines_reports_line_numbers() {
+        let path = std::env::temp_dir().join(format!("cryptopals_load_lines_test_{}", std::process::id()));
+        std::fs::write(&path, "Zm9v\n\nYmFy\nYm*y\n").unwrap();
+        let auto = load_lines(path.to_str().unwrap(), LineEncoding::Auto);
+        let hex = load_lines(path.to_str().unwrap(), LineEncoding::Hex);
+        std::fs::write(&path, "Zm9v\n\nYmFy\n").unwrap();
+        let numbered = load_numbered_lines(path.to_str().unwrap(), LineEncoding::Auto);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(numbered, Ok(vec![(1, b"foo".to_vec()), (3, b"bar".to_vec())]));
+
+        assert_eq!(auto, Err(Error::AtLine(4, Box::new(Error::InvalidBase64String))));
+        assert_eq!(hex, Err(Error::AtLine(1, Box::new(Error::InvalidHexString))));
+    }
+
+    #[test]
+    fn detect_encoding_works() {
+        assert_eq!(detect_encoding(&["0123", "abcdef"]), LineEncoding::Hex);
+        assert_eq!(detect_encoding(&["0123", "abcdeg"]), LineEncoding::Base64);
+        assert_eq!(detect_encoding(&["0123", "abc"]), LineEncoding::Base64);
+    }
+
     #[test]
     fn hamming_distance_works() {
         assert_eq!(hamming_distance(b"this is a test", b"wokka wokka!!!"), Ok(37));
-        assert_eq!(hamming_distance(b"this is a test", b"wrong size"), Err(Error::NotEqualSizeError));
+        assert_eq!(hamming_distance(b"this is a test", b"wrong size"), Err(Error::NotEqualSize));
     }
 
     #[test]
+    #[allow(clippy::unnecessary_cast)]
     fn english_score_works() {
         assert!(english_score("Hi, how are you?")/11 as f64 > english_score("Hola, ¿como estas?")/13 as f64);
         assert!(english_score("Hi, how are you?")/11 as f64 > english_score("Ciao, come va?")/10 as f64);